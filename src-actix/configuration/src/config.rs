@@ -28,6 +28,35 @@ pub struct ObsidianConfig {
     pub backups_directory: String,
     /// Directory where Java is installed.
     pub java_install_directory: String,
+    /// Delay in seconds between starting two auto-start servers when the panel boots.
+    #[serde(default)]
+    pub auto_start_delay_seconds: u64,
+    /// Flag to kill the servers still running from before the panel restarted instead of adopting them.
+    #[serde(default)]
+    pub kill_orphaned_servers: bool,
+    /// Flag to stop the running servers when the panel shuts down instead of leaving them running to be adopted.
+    #[serde(default = "default_stop_servers_on_shutdown")]
+    pub stop_servers_on_shutdown: bool,
+    /// Time in seconds the servers get to stop when the panel shuts down,
+    /// including terminating the ones that don't stop on their own.
+    #[serde(default = "default_shutdown_timeout_seconds")]
+    pub shutdown_timeout_seconds: u64,
+    /// Interval in seconds at which the players of the online servers are listed to correct their rosters,
+    /// 0 to only list them when asked to.
+    #[serde(default = "default_player_list_interval_seconds")]
+    pub player_list_interval_seconds: u64,
+}
+
+fn default_stop_servers_on_shutdown() -> bool {
+    true
+}
+
+fn default_shutdown_timeout_seconds() -> u64 {
+    60
+}
+
+fn default_player_list_interval_seconds() -> u64 {
+    300
 }
 
 impl ObsidianConfig {
@@ -98,6 +127,11 @@ impl ObsidianConfig {
             servers_directory: servers.normalize().to_str().unwrap().to_string(),
             backups_directory: backups.normalize().to_str().unwrap().to_string(),
             java_install_directory: java.normalize().to_str().unwrap().to_string(),
+            auto_start_delay_seconds: 0,
+            kill_orphaned_servers: false,
+            stop_servers_on_shutdown: default_stop_servers_on_shutdown(),
+            shutdown_timeout_seconds: default_shutdown_timeout_seconds(),
+            player_list_interval_seconds: default_player_list_interval_seconds(),
         })
     }
 }
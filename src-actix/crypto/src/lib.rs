@@ -1,2 +1,3 @@
 pub mod hashids;
+pub mod random;
 pub mod salt;
@@ -0,0 +1,11 @@
+use rand::distr::Alphanumeric;
+use rand::Rng;
+
+/// Generates a random alphanumeric string, e.g. for a generated password.
+pub fn random_alphanumeric(length: usize) -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
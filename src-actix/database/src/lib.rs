@@ -6,6 +6,9 @@
 use log::error;
 use std::error::Error;
 
+/// How long a connection waits for a lock held by another connection, in milliseconds.
+const BUSY_TIMEOUT_MS: usize = 5000;
+
 /// Establishes a connection to the app's SQLite database.
 ///
 /// # Returns
@@ -26,7 +29,11 @@ pub fn create_appdb_connection() -> Result<sqlite::Connection, sqlite::Error> {
         error!("Failed to open apps database connection: {}", e);
         e
     }) {
-        Ok(conn) => {
+        Ok(mut conn) => {
+            // waits for other connections to finish writing instead of failing with `database is locked`
+            if let Err(e) = conn.set_busy_timeout(BUSY_TIMEOUT_MS) {
+                error!("Failed to set the busy timeout: {}", e);
+            }
             // allows multiple connections to the database
             match conn.execute("PRAGMA journal_mode = WAL;") {
                 Ok(_) => {}
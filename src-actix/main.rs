@@ -10,6 +10,8 @@ mod minecraft_endpoint;
 mod notifications_endpoint;
 mod server_endpoint;
 mod server_properties_endpoint;
+mod server_schedule_endpoint;
+mod shutdown;
 mod system_stats_endpoint;
 
 use actix_files::file_extension_to_mime;
@@ -24,10 +26,11 @@ use futures_util::stream::StreamExt;
 use include_dir::{include_dir, Dir};
 use log::{debug, error, info};
 use network_utility::{close_all_ports, open_port};
-use scheduler::{start_ticking_schedules, stop_ticking_schedules};
+use scheduler::start_ticking_schedules;
 use serde_json::json;
 use std::process::{exit, Child};
 use std::sync::Mutex;
+use std::time::Duration;
 use sysinfo::System;
 
 const DEBUG: bool = cfg!(debug_assertions);
@@ -52,6 +55,36 @@ async fn main() -> std::io::Result<()> {
         }
     }
     backups::initialize();
+    if let Err(e) = servers::server_schedules::load_schedules() {
+        error!("Failed to load the server schedules: {}", e);
+    }
+    servers::server_players::set_player_list_interval(Duration::from_secs(CONFIG.player_list_interval_seconds));
+    if let Err(e) = servers::idle_policy::start_idle_monitor() {
+        error!("Failed to start the idle monitor: {}", e);
+    }
+
+    // Recover the servers that kept running while the panel was down, then start the servers flagged with
+    // auto-start. This runs in the background so a slow server doesn't delay the web ui
+    std::thread::spawn(|| {
+        if let Err(e) = servers::server_process::recover_server_processes(CONFIG.kill_orphaned_servers) {
+            error!("Failed to recover running servers: {}", e);
+        }
+
+        let stagger = Duration::from_secs(CONFIG.auto_start_delay_seconds);
+        match servers::auto_start::start_auto_start_servers(stagger) {
+            Ok(results) => info!(
+                "Auto-started {} of {} servers",
+                results.iter().filter(|result| result.error.is_none()).count(),
+                results.len()
+            ),
+            Err(e) => error!("Failed to auto-start servers: {}", e),
+        }
+
+        // The servers that were sleeping and weren't auto-started wait for players again
+        if let Err(e) = servers::wake_listener::resume_wake_listeners() {
+            error!("Failed to resume the wake listeners: {}", e);
+        }
+    });
 
     if CONFIG.port_forward_webui {
         // This will open the webui port on the router using upnp
@@ -123,6 +156,7 @@ async fn main() -> std::io::Result<()> {
                             .service(web::scope("files").service(file_system_endpoint::get_files))
                             .service(server_endpoint::get_servers)
                             .service(server_endpoint::create_server)
+                            .service(server_endpoint::get_auto_start_report)
                             .service(
                                 web::scope("{id}")
                                     .service(
@@ -140,6 +174,17 @@ async fn main() -> std::io::Result<()> {
                                             .service(file_system_endpoint::create_file)
                                             .service(file_system_endpoint::delete_path),
                                     )
+                                    .service(
+                                        web::scope("schedules")
+                                            .service(server_schedule_endpoint::get_server_schedules)
+                                            .service(server_schedule_endpoint::create_server_schedule)
+                                            // Registered before `/{schedule}`, which would match it too
+                                            .service(server_schedule_endpoint::get_server_task_history)
+                                            .service(server_schedule_endpoint::get_server_schedule)
+                                            .service(server_schedule_endpoint::update_server_schedule)
+                                            .service(server_schedule_endpoint::delete_server_schedule)
+                                            .service(server_schedule_endpoint::run_server_schedule),
+                                    )
                                     .service(
                                         web::scope("backups")
                                             .service(backups_endpoint::get_backups)
@@ -149,10 +194,34 @@ async fn main() -> std::io::Result<()> {
                                     .service(server_endpoint::delete_server)
                                     .service(server_endpoint::get_server_icon)
                                     .service(server_endpoint::start_server)
+                                    .service(server_endpoint::validate_start)
+                                    .service(server_endpoint::preview_start)
+                                    .service(server_endpoint::get_server_eula)
+                                    .service(server_endpoint::accept_server_eula)
+                                    .service(server_endpoint::stop_server)
+                                    .service(server_endpoint::restart_server)
+                                    .service(server_endpoint::kill_server)
+                                    .service(server_endpoint::get_server_crashes)
+                                    .service(server_endpoint::get_server_players)
+                                    .service(server_endpoint::get_server_player_sessions)
+                                    .service(server_endpoint::get_server_recent_players)
+                                    .service(server_endpoint::get_server_resources)
+                                    .service(server_endpoint::get_server_usage)
+                                    .service(server_endpoint::get_server_usage_sse)
                                     .service(server_endpoint::send_command)
+                                    .service(server_endpoint::ping_server)
+                                    .service(server_endpoint::query_server)
+                                    .service(server_endpoint::get_server_query_settings)
+                                    .service(server_endpoint::set_server_query_settings)
+                                    .service(server_endpoint::get_server_environment)
+                                    .service(server_endpoint::set_server_environment)
+                                    .service(server_endpoint::get_server_rcon)
+                                    .service(server_endpoint::set_server_rcon)
                                     .service(server_endpoint::get_server_console)
                                     .service(server_endpoint::get_server_state_updates)
-                                    .service(server_endpoint::get_server_console_sse),
+                                    .service(server_endpoint::get_server_log_events)
+                                    .service(server_endpoint::get_server_console_sse)
+                                    .service(server_endpoint::get_server_console_ws),
                             ),
                     )
                     .service(web::scope("instances").service(instance_endpoint::discover_modpacks))
@@ -173,8 +242,11 @@ async fn main() -> std::io::Result<()> {
         }
     })
     .workers(4)
+    // The shutdown coordinator stops the servers before the web server
+    .disable_signals()
     .bind(format!("0.0.0.0:{port}", port = port))?
     .run();
+    rt::spawn(shutdown::handle_shutdown_signals(server.handle()));
     info!(
         "Starting {} server at http://127.0.0.1:{}...",
         if DEBUG { "development" } else { "production" },
@@ -185,15 +257,10 @@ async fn main() -> std::io::Result<()> {
         start_vite_server().expect("Failed to start vite server");
     }
 
+    // The schedules are stopped by the shutdown handler, before it stops the servers.
     let stop_result = server.await;
     debug!("Server stopped");
 
-    // Stop all schedules
-    // This is necessary to prevent the server from hanging on shutdown
-    // because the schedules are still running
-    // This is probably not necessary in a production environment
-    stop_ticking_schedules!();
-
     // Closes all open ports
     close_all_ports!();
     stop_result
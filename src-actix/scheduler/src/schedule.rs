@@ -25,6 +25,16 @@ impl Schedule {
         }
     }
 
+    /// Creates a schedule that first runs at `start` instead of after `duration`.
+    pub fn starting_at<F>(id: u64, start: SystemTime, duration: Duration, reoccurring: bool, action: F) -> Schedule
+    where
+        F: Fn(&Self) + 'static + Send + Sync,
+    {
+        let mut schedule = Schedule::new(id, duration, reoccurring, action);
+        schedule.end_time = start;
+        schedule
+    }
+
     pub fn get_end_time(&self) -> SystemTime {
         self.end_time
     }
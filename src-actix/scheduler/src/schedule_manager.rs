@@ -2,6 +2,7 @@ use crate::duration::Duration;
 use crate::schedule::Schedule;
 use lazy_static::lazy_static;
 use std::sync::Mutex;
+use std::time::SystemTime;
 
 lazy_static! {
     pub static ref SCHEDULE_MANAGER_SINGLETON: Mutex<ScheduleManager> = Mutex::new(ScheduleManager::new());
@@ -49,6 +50,24 @@ impl ScheduleManager {
         id
     }
 
+    /// Adds a schedule that first runs at `start`, then every `duration` if it's reoccurring.
+    pub fn add_schedule_starting_at<F>(
+        &mut self,
+        start: SystemTime,
+        duration: Duration,
+        reoccurring: bool,
+        action: F,
+    ) -> u64
+    where
+        F: Fn(&Schedule) + 'static + Send + Sync,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.schedules
+            .push(Schedule::starting_at(id, start, duration, reoccurring, action));
+        id
+    }
+
     pub fn remove_schedule<F>(&mut self, filter: F)
     where
         F: Fn(&Schedule) -> bool,
@@ -1,28 +1,50 @@
 use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use actix_web_lab::sse;
 use actix_web_lab::sse::Event;
+use actix_ws::AggregatedMessage;
 use authentication::data::User;
 use crypto::hashids::decode;
+use futures_util::stream::StreamExt;
 use loader_manager::supported_loaders::Loader;
-use log::{debug, error, info};
+use log::{debug, error};
 use minecraft::minecraft_version::download_server_jar;
 use percent_encoding::percent_decode;
-use serde::Deserialize;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use servers::auto_start;
+use servers::cgroup::read_cgroup_usage;
+use servers::console::{console_hub, ConsoleLine};
+use servers::jvm_options::{GarbageCollector, JvmPreset};
+use servers::log_events::parse_log_file;
+use servers::rcon::ServerRcon;
+use servers::readiness::ReadinessProbe;
+use servers::resource_limits::{CPU_WEIGHT_RANGE, MEMORY_MAX_MB_LIMIT};
+use servers::restart_policy::RestartMode;
 use servers::server::Server;
+use servers::server_crash::get_crashes;
 use servers::server_database::ServerDatabase;
+use servers::server_eula::ServerEula;
 use servers::server_filesystem::ServerFilesystem;
-use servers::server_process::ServerProcess;
+use servers::server_list_ping::ServerListPing;
+use servers::server_players::{get_recent_players, get_sessions, online_players, refresh_players};
+use servers::server_process::{ServerProcess, DEFAULT_STOP_TIMEOUT};
 use servers::server_properties::ServerProperties;
-use std::collections::HashMap;
+use servers::server_query::ServerQuery;
+use servers::server_usage::{self, UsageMonitor};
+use servers::start_command::{check_environment_variable, check_start_command};
+use servers::start_validation::{StartValidation, StartValidationError, ValidationCheck, ValidationSeverity};
+use servers::wake_listener::update_wake_listener;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::{From, Into};
 use std::error::Error;
 use std::ops::RangeTo;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::time::interval;
 
@@ -54,6 +76,15 @@ pub async fn get_servers(req: HttpRequest) -> Result<impl Responder, Box<dyn Err
     Ok(HttpResponse::Unauthorized().json(json!({"error":"Unauthorized"})))
 }
 
+// Retrieves the outcome of starting the auto-start servers when the panel booted
+#[get("/auto-start")]
+pub async fn get_auto_start_report(req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        return Ok(HttpResponse::Ok().json(auto_start::get_auto_start_report(user.id as u64)));
+    }
+    Ok(HttpResponse::Unauthorized().json(json!({"error":"Unauthorized"})))
+}
+
 // Retrieves a specific server by its ID, ensuring the server is owned by the authenticated user
 #[get("")]
 pub async fn get_server_by_id(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
@@ -122,8 +153,8 @@ pub async fn create_server(
         server.loader_version = body.loader_version.clone();
         server.minecraft_version = body.minecraft_version.clone();
         server.java_runtime = Some(PathBuf::from(body.java_path.clone()));
-        server.min_ram = 1;
-        server.max_ram = 4;
+        server.min_ram = 1024;
+        server.max_ram = 4096;
 
         // Create the directory for the server, ensuring a valid and unique directory name
         server.create_server_directory()?;
@@ -311,18 +342,18 @@ pub async fn set_setting(id: web::Path<String>, req: HttpRequest) -> Result<impl
             server.name = v.clone();
         }
 
-        // Try to retrieve the "min-ram" parameter from the parameters map.
+        // Try to retrieve the "min-ram" parameter from the parameters map, in MB.
         // If the value can be parsed into a `u64`, update the server's min_ram attribute.
         if let Some(v) = parameters.get("min-ram").and_then(|v| u64::from_str(v).ok()) {
             server.min_ram = v;
-            println!("Min RAM: {}", server.min_ram);
+            debug!("Min RAM: {} MB", server.min_ram);
         }
 
-        // Attempt to retrieve the "max-ram" parameter from the `parameters` map.
+        // Attempt to retrieve the "max-ram" parameter from the `parameters` map, in MB.
         // If the value exists, try to parse it as an unsigned 64-bit integer (`u64`).
         if let Some(v) = parameters.get("max-ram").and_then(|v| u64::from_str(v).ok()) {
             server.max_ram = v;
-            println!("Min RAM: {}", server.max_ram);
+            debug!("Max RAM: {} MB", server.max_ram);
         }
 
         // Retrieve the "auto-start" parameter, if present, convert it to lowercase,
@@ -331,6 +362,11 @@ pub async fn set_setting(id: web::Path<String>, req: HttpRequest) -> Result<impl
             server.auto_start = v.to_lowercase() == "true";
         }
 
+        // Check for the "auto-start-order" parameter, lower values are started first when the panel boots.
+        if let Some(v) = parameters.get("auto-start-order").and_then(|v| u32::from_str(v).ok()) {
+            server.auto_start_order = v;
+        }
+
         // Check for the "start-script" parameter.
         // If present, convert it to a `PathBuf` and update the server's start_script value.
         if let Some(v) = parameters.get("start-script") {
@@ -343,43 +379,618 @@ pub async fn set_setting(id: web::Path<String>, req: HttpRequest) -> Result<impl
             server.minecraft_arguments = Some(v.clone());
         }
 
+        // Check for the "start-command" parameter, a template that replaces the command built from the start script.
+        // An empty value goes back to the start script.
+        if let Some(v) = parameters.get("start-command") {
+            let template = Some(v.clone()).filter(|v| !v.trim().is_empty());
+            if let Some(Err(e)) = template.as_deref().map(check_start_command) {
+                return Ok(HttpResponse::BadRequest().json(json!({"message":format!("Invalid start command: {}", e)})));
+            }
+            server.start_command = template;
+        }
+
         // Check for the "java-arguments" parameter.
         // If found, clone the value and update the server's java_arguments attribute.
         if let Some(v) = parameters.get("java-arguments") {
             server.java_arguments = Some(v.clone());
         }
 
+        // Check for the "jvm-preset" parameter, one of "none", "aikar" or "zgc".
+        if let Some(v) = parameters.get("jvm-preset") {
+            server.jvm_options.preset = match JvmPreset::from_str(v) {
+                Ok(preset) => preset,
+                Err(_) => {
+                    return Ok(HttpResponse::BadRequest().json(json!({"message":format!("Invalid JVM preset: {}", v)})))
+                }
+            };
+        }
+
+        // Check for the "jvm-gc" parameter, the garbage collector for servers without a preset, empty or "none" removes it.
+        if let Some(v) = parameters.get("jvm-gc") {
+            server.jvm_options.gc = match v.as_str() {
+                "" | "none" => None,
+                v => match GarbageCollector::from_str(v) {
+                    Ok(gc) => Some(gc),
+                    Err(_) => {
+                        return Ok(HttpResponse::BadRequest()
+                            .json(json!({"message":format!("Invalid garbage collector: {}", v)})))
+                    }
+                },
+            };
+        }
+
+        // Check for the "restart-policy" parameter, one of "never", "on-crash" or "always".
+        if let Some(v) = parameters.get("restart-policy") {
+            server.restart_policy.mode = match RestartMode::from_str(v) {
+                Ok(mode) => mode,
+                Err(_) => {
+                    return Ok(
+                        HttpResponse::BadRequest().json(json!({"message":format!("Invalid restart policy: {}", v)}))
+                    )
+                }
+            };
+        }
+
+        // The remaining restart policy parameters limit how often and how fast the server is restarted.
+        if let Some(v) = parameters
+            .get("restart-max-attempts")
+            .and_then(|v| u32::from_str(v).ok())
+        {
+            server.restart_policy.max_attempts = v;
+        }
+        if let Some(v) = parameters.get("restart-window").and_then(|v| u64::from_str(v).ok()) {
+            server.restart_policy.window_seconds = v;
+        }
+        if let Some(v) = parameters.get("restart-backoff").and_then(|v| u64::from_str(v).ok()) {
+            server.restart_policy.backoff_seconds = v;
+        }
+        if let Some(v) = parameters
+            .get("restart-max-backoff")
+            .and_then(|v| u64::from_str(v).ok())
+        {
+            server.restart_policy.max_backoff_seconds = v;
+        }
+
+        // The resource limits apply the next time the server starts, an empty value or "none" removes a limit.
+        let limits = &mut server.resource_limits;
+        let valid = parse_limit(&parameters, "cpu-weight", &mut limits.cpu_weight)
+            && parse_limit(&parameters, "cpu-quota", &mut limits.cpu_quota_percent)
+            && parse_limit(&parameters, "memory-max", &mut limits.memory_max_mb)
+            && parse_limit(&parameters, "pids-max", &mut limits.pids_max)
+            && limits
+                .cpu_weight
+                .is_none_or(|weight| CPU_WEIGHT_RANGE.contains(&weight))
+            && limits.memory_max_mb.is_none_or(|mb| mb <= MEMORY_MAX_MB_LIMIT);
+        if !valid {
+            let message = format!(
+                "Invalid resource limits, they have to be positive numbers, cpu-weight at most 10000 and memory-max at most {}",
+                MEMORY_MAX_MB_LIMIT
+            );
+            return Ok(HttpResponse::BadRequest().json(json!({ "message": message })));
+        }
+
+        // Check for the "ready-probe" parameter, one of "loader", "console-pattern", "tcp-port" or "server-list-ping".
+        if let Some(v) = parameters.get("ready-probe") {
+            server.readiness.probe = match ReadinessProbe::from_str(v) {
+                Ok(probe) => probe,
+                Err(_) => {
+                    return Ok(HttpResponse::BadRequest().json(json!({"message":format!("Invalid ready probe: {}", v)})))
+                }
+            };
+        }
+
+        // The console pattern and the probed port fall back to the defaults when they're empty.
+        if let Some(v) = parameters.get("ready-pattern") {
+            if let Err(e) = Regex::new(v) {
+                return Ok(HttpResponse::BadRequest().json(json!({"message":format!("Invalid ready pattern: {}", e)})));
+            }
+            server.readiness.pattern = Some(v.clone()).filter(|v| !v.is_empty());
+        }
+        if let Some(v) = parameters.get("ready-port") {
+            server.readiness.port = match v.trim() {
+                "" => None,
+                v => match u16::from_str(v) {
+                    Ok(port) if port > 0 => Some(port),
+                    _ => {
+                        return Ok(HttpResponse::BadRequest().json(
+                            json!({"message":format!("Invalid ready port, expected 1 to 65535 or empty: {}", v)}),
+                        ))
+                    }
+                },
+            };
+        }
+        if let Some(v) = parameters.get("ready-timeout") {
+            server.readiness.timeout_seconds = match u64::from_str(v) {
+                Ok(seconds) if seconds > 0 => seconds,
+                _ => {
+                    return Ok(HttpResponse::BadRequest()
+                        .json(json!({"message":format!("Invalid ready timeout, expected at least 1 second: {}", v)})))
+                }
+            };
+        }
+
+        // Check for the "idle-shutdown" parameter, which stops the server after "idle-minutes" without players.
+        if let Some(v) = parameters.get("idle-shutdown") {
+            server.idle_policy.enabled = v.to_lowercase() == "true";
+        }
+        if let Some(v) = parameters.get("idle-minutes") {
+            server.idle_policy.idle_minutes = match u64::from_str(v) {
+                Ok(minutes) if minutes > 0 => minutes,
+                _ => {
+                    return Ok(HttpResponse::BadRequest()
+                        .json(json!({"message":format!("Invalid idle minutes, expected at least 1: {}", v)})))
+                }
+            };
+        }
+        // While the server sleeps, the panel answers pings with "idle-motd" and kicks joining players with
+        // "idle-kick-message" while it starts the server, unless "wake-on-connect" is false.
+        if let Some(v) = parameters.get("wake-on-connect") {
+            server.idle_policy.wake_on_connect = v.to_lowercase() == "true";
+        }
+        if let Some(v) = parameters.get("idle-motd") {
+            server.idle_policy.motd = v.clone();
+        }
+        if let Some(v) = parameters.get("idle-kick-message") {
+            server.idle_policy.kick_message = v.clone();
+        }
+
         server.update()?;
+        update_wake_listener(&server)?;
         return Ok(HttpResponse::Ok().finish());
     }
     Ok(HttpResponse::Unauthorized().json(json!({"message":"User not authenticated"})))
 }
 
+/// Updates an optional resource limit from the settings parameters, if the parameter is present.
+///
+/// # Returns
+/// `false` if the parameter is present but isn't a positive number, "none" or empty.
+fn parse_limit<T: FromStr + PartialOrd + Default>(
+    parameters: &HashMap<String, String>,
+    name: &str,
+    limit: &mut Option<T>,
+) -> bool {
+    match parameters.get(name).map(|v| v.trim()) {
+        None => true,
+        Some("") | Some("none") => {
+            *limit = None;
+            true
+        }
+        Some(v) => match T::from_str(v) {
+            Ok(v) if v > T::default() => {
+                *limit = Some(v);
+                true
+            }
+            _ => false,
+        },
+    }
+}
+
+#[get("/resources")]
+pub async fn get_server_resources(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        // The usage is only available while the server runs in its cgroup.
+        return Ok(HttpResponse::Ok().json(json!({
+            "limits": server.resource_limits,
+            "usage": read_cgroup_usage(server.id),
+        })));
+    }
+    Ok(HttpResponse::Unauthorized().finish())
+}
+
 #[post("/start")]
 pub async fn start_server(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let id = decode(id.as_str()).map(|id_number| id_number[0])?;
+    let mut server = Server::get_owned_server(id, user_id)?;
+
+    // Starting probes the port, runs the Java runtime and sets up the console, so keep it off the async workers.
+    // The error isn't `Send`, so the validation errors are taken out of it before leaving the blocking thread.
+    let started = web::block(move || {
+        server.start_server().map_err(|e| {
+            e.downcast::<StartValidationError>()
+                .map(|validation| *validation)
+                .map_err(|e| e.to_string())
+        })
+    })
+    .await?;
+    match started {
+        Ok(_) => Ok(HttpResponse::Ok().finish()),
+        // Validation errors are the user's to fix, so they're reported with every issue found.
+        Err(Ok(validation)) => {
+            // Lets the client ask the user to accept the EULA instead of only showing the error.
+            let eula_required = validation.has_error(ValidationCheck::Eula);
+            let body = json!({ "issues": validation.issues, "eula_required": eula_required });
+            Ok(HttpResponse::BadRequest().json(body))
+        }
+        Err(Err(e)) => Err(e.into()),
+    }
+}
+
+#[get("/start/validate")]
+pub async fn validate_start(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let server = Server::get_owned_server_from_string(id.as_ref(), user_id)?;
+
+    // The checks read files and bind the port, so keep them off the async workers.
+    let issues = web::block(move || server.validate_start()).await?;
+    let can_start = !issues.iter().any(|issue| issue.severity == ValidationSeverity::Error);
+    Ok(HttpResponse::Ok().json(json!({ "can_start": can_start, "issues": issues })))
+}
+
+/// Returns the exact command `start` runs for the server, with the JVM options applied.
+#[get("/start/preview")]
+pub async fn preview_start(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let server = Server::get_owned_server_from_string(id.as_ref(), user_id)?;
+
+    // Detecting the Java version may run the runtime, so keep it off the async workers.
+    let preview = web::block(move || server.preview_command().map_err(|e| e.to_string())).await?;
+    Ok(match preview {
+        Ok(preview) => HttpResponse::Ok().json(preview),
+        Err(e) => HttpResponse::BadRequest().json(json!({ "error": e })),
+    })
+}
+
+#[get("/eula")]
+pub async fn get_server_eula(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
     if let Some(user) = req.extensions().get::<User>() {
-        let id = decode(id.as_str()).map(|id_number| id_number[0])?;
-        let mut server = Server::get_owned_server(id, user.id as u64)?;
-        server.start_server()?;
-        return Ok(HttpResponse::Ok().finish());
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        return Ok(HttpResponse::Ok().json(server.get_eula_status()?));
     }
     Ok(HttpResponse::Unauthorized().finish())
 }
 
-#[post("/send-command")]
-pub async fn send_command(
+/// Accepts the Minecraft EULA for the server on behalf of the authenticated user.
+#[post("/eula")]
+pub async fn accept_server_eula(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        server.accept_eula(user.id as u64)?;
+        return Ok(HttpResponse::Ok().json(server.get_eula_status()?));
+    }
+    Ok(HttpResponse::Unauthorized().finish())
+}
+
+#[post("/stop")]
+pub async fn stop_server(
     id: web::Path<String>,
-    body: String,
+    query: web::Query<HashMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let id = decode(id.as_str()).map(|id_number| id_number[0])?;
+    let mut server = Server::get_owned_server(id, user_id)?;
+
+    // The grace period in seconds before the process is forcefully terminated.
+    let timeout = query
+        .get("timeout")
+        .and_then(|v| u64::from_str(v).ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_STOP_TIMEOUT);
+
+    // Stopping blocks until the process exits, so keep it off the async workers.
+    web::block(move || server.stop_server_with_timeout(timeout).map_err(|e| e.to_string())).await??;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[post("/restart")]
+pub async fn restart_server(
+    id: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let id = decode(id.as_str()).map(|id_number| id_number[0])?;
+    let mut server = Server::get_owned_server(id, user_id)?;
+
+    // The grace period in seconds before the process is forcefully terminated.
+    let timeout = query
+        .get("timeout")
+        .and_then(|v| u64::from_str(v).ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_STOP_TIMEOUT);
+
+    // Restarting waits for the old process to exit, so keep it off the async workers.
+    web::block(move || server.restart_server_with_timeout(timeout).map_err(|e| e.to_string())).await??;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[post("/kill")]
+pub async fn kill_server(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let id = decode(id.as_str()).map(|id_number| id_number[0])?;
+    let mut server = Server::get_owned_server(id, user_id)?;
+
+    // Killing waits for the process to exit, so keep it off the async workers.
+    web::block(move || server.kill_server().map_err(|e| e.to_string())).await??;
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[get("/crashes")]
+pub async fn get_server_crashes(
+    id: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
     req: HttpRequest,
 ) -> Result<impl Responder, Box<dyn Error>> {
     if let Some(user) = req.extensions().get::<User>() {
         let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
-        server.send_command_to_server(body)?;
-        return Ok(HttpResponse::Ok().finish());
+        let limit = query.get("limit").and_then(|v| u64::from_str(v).ok()).unwrap_or(20);
+        let crashes = get_crashes(server.id, limit)?;
+        return Ok(HttpResponse::Ok().json(crashes));
+    }
+    Ok(HttpResponse::Unauthorized().finish())
+}
+
+/// Lists the players on the server. With `refresh=true` the roster is first checked against the `list` command.
+#[get("/players")]
+pub async fn get_server_players(
+    id: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let server = Server::get_owned_server_from_string(id.as_ref(), user_id)?;
+
+    if query.get("refresh").is_some_and(|refresh| refresh == "true") {
+        // Waiting for the response to `list` blocks, so keep it off the async workers.
+        let players = web::block(move || refresh_players(&server).map_err(|e| e.to_string())).await??;
+        return Ok(HttpResponse::Ok().json(players));
+    }
+    Ok(HttpResponse::Ok().json(online_players(server.id)))
+}
+
+#[get("/players/sessions")]
+pub async fn get_server_player_sessions(
+    id: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        let limit = query.get("limit").and_then(|v| u64::from_str(v).ok()).unwrap_or(50);
+        let sessions = get_sessions(server.id, query.get("player").map(String::as_str), limit)?;
+        return Ok(HttpResponse::Ok().json(sessions));
+    }
+    Ok(HttpResponse::Unauthorized().finish())
+}
+
+#[get("/players/recent")]
+pub async fn get_server_recent_players(
+    id: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        let limit = query.get("limit").and_then(|v| u64::from_str(v).ok()).unwrap_or(50);
+        return Ok(HttpResponse::Ok().json(get_recent_players(server.id, limit)?));
+    }
+    Ok(HttpResponse::Unauthorized().finish())
+}
+
+/// Pings the server through the Server List Ping protocol, answering `503` if it doesn't respond,
+/// so it can be used as a health check.
+#[get("/ping")]
+pub async fn ping_server(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let server = Server::get_owned_server_from_string(id.as_ref(), user_id)?;
+    let response = web::block(move || server.ping_server().map_err(|e| e.to_string())).await?;
+    Ok(match response {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => HttpResponse::ServiceUnavailable().json(json!({ "error": e })),
+    })
+}
+
+/// Queries the server through the GameSpy4 query protocol, the full stat with every player and
+/// plugin unless `basic=true` is given.
+#[get("/query")]
+pub async fn query_server(
+    id: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let server = Server::get_owned_server_from_string(id.as_ref(), user_id)?;
+    let basic = query.get("basic").is_some_and(|basic| basic == "true");
+    let response = web::block(move || {
+        if basic {
+            server.query_server_basic().map(|stat| json!(stat))
+        } else {
+            server.query_server().map(|stat| json!(stat))
+        }
+        .map_err(|e| e.to_string())
+    })
+    .await?;
+    Ok(match response {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(e) => HttpResponse::ServiceUnavailable().json(json!({ "error": e })),
+    })
+}
+
+#[get("/query/settings")]
+pub async fn get_server_query_settings(
+    id: web::Path<String>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        return Ok(HttpResponse::Ok().json(server.query_settings()?));
     }
     Ok(HttpResponse::Unauthorized().finish())
 }
 
+/// Turns the query protocol on or off with the `enabled` query parameter, picking a free port when enabling.
+#[post("/query/settings")]
+pub async fn set_server_query_settings(
+    id: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        let Some(enabled) = query.get("enabled").and_then(|enabled| bool::from_str(enabled).ok()) else {
+            return Ok(HttpResponse::BadRequest().json(json!({"error":"Missing or invalid enabled parameter"})));
+        };
+        return Ok(HttpResponse::Ok().json(server.set_query_enabled(enabled)?));
+    }
+    Ok(HttpResponse::Unauthorized().finish())
+}
+
+#[get("/environment")]
+pub async fn get_server_environment(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        return Ok(HttpResponse::Ok().json(server.environment));
+    }
+    Ok(HttpResponse::Unauthorized().finish())
+}
+
+/// Replaces the environment variables of the server with the ones in the body, applied on its next start.
+#[post("/environment")]
+pub async fn set_server_environment(
+    id: web::Path<String>,
+    body: web::Json<BTreeMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let mut server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        let environment = body.into_inner();
+        for (name, value) in &environment {
+            if let Err(e) = check_environment_variable(name, value) {
+                return Ok(HttpResponse::BadRequest().json(json!({ "error": e })));
+            }
+        }
+        server.environment = environment;
+        server.update()?;
+        return Ok(HttpResponse::Ok().json(server.environment));
+    }
+    Ok(HttpResponse::Unauthorized().finish())
+}
+
+#[get("/rcon")]
+pub async fn get_server_rcon(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        return Ok(HttpResponse::Ok().json(server.rcon_settings()?));
+    }
+    Ok(HttpResponse::Unauthorized().finish())
+}
+
+/// Turns RCON on or off with the `enabled` query parameter, generating a password and port when enabling.
+#[post("/rcon")]
+pub async fn set_server_rcon(
+    id: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        let Some(enabled) = query.get("enabled").and_then(|enabled| bool::from_str(enabled).ok()) else {
+            return Ok(HttpResponse::BadRequest().json(json!({"error":"Missing or invalid enabled parameter"})));
+        };
+        return Ok(HttpResponse::Ok().json(server.set_rcon_enabled(enabled)?));
+    }
+    Ok(HttpResponse::Unauthorized().finish())
+}
+
+/// How long `send-command` collects the response when only `until` is given, in milliseconds.
+const DEFAULT_CAPTURE_WINDOW_MS: u64 = 5000;
+/// The longest `send-command` collects the response for, in milliseconds.
+const MAX_CAPTURE_WINDOW_MS: u64 = 30000;
+
+/// Sends a command to the server's console.
+///
+/// With the `capture` query parameter (a window in milliseconds) or `until` (a regular expression),
+/// the console lines printed after the command are collected and returned, until the window runs
+/// out or a line matches `until`. With `transport=rcon` the command is sent over RCON and its
+/// response is returned directly.
+#[post("/send-command")]
+pub async fn send_command(
+    id: web::Path<String>,
+    body: String,
+    query: web::Query<HashMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let server = Server::get_owned_server_from_string(id.as_ref(), user_id)?;
+
+    if query.get("transport").is_some_and(|transport| transport == "rcon") {
+        let response = web::block(move || server.send_rcon_command(body).map_err(|e| e.to_string())).await??;
+        return Ok(HttpResponse::Ok().json(json!({ "response": response })));
+    }
+
+    let until = match query.get("until").map(|until| Regex::new(until)).transpose() {
+        Ok(until) => until,
+        Err(e) => return Ok(HttpResponse::BadRequest().json(json!({"error": format!("Invalid pattern: {}", e)}))),
+    };
+    let window = match query.get("capture") {
+        Some(window) => match u64::from_str(window) {
+            Ok(window) => Some(window),
+            Err(_) => return Ok(HttpResponse::BadRequest().json(json!({"error":"Invalid capture window"}))),
+        },
+        None => until.as_ref().map(|_| DEFAULT_CAPTURE_WINDOW_MS),
+    };
+
+    let Some(window) = window else {
+        // Without a console input the command goes through RCON, which connects to the server, so keep it off
+        // the async workers as well.
+        web::block(move || server.send_command_to_server(body).map_err(|e| e.to_string())).await??;
+        return Ok(HttpResponse::Ok().finish());
+    };
+
+    let window = Duration::from_millis(window.min(MAX_CAPTURE_WINDOW_MS));
+    // Capturing waits for the response, so keep it off the async workers.
+    let capture = web::block(move || {
+        server
+            .send_command_and_capture(body, window, until.as_ref())
+            .map_err(|e| e.to_string())
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(capture))
+}
+
 #[get("/console")]
 pub async fn get_server_console(
     id: web::Path<String>,
@@ -395,6 +1006,32 @@ pub async fn get_server_console(
     Ok(HttpResponse::Unauthorized().finish())
 }
 
+/// Parses a log file in the server's `logs` directory into events, `latest.log` if no `log_file` is given.
+#[get("/logs/events")]
+pub async fn get_server_log_events(
+    id: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let server = Server::get_owned_server_from_string(id.as_ref(), user_id)?;
+
+    // Only files directly inside the logs directory can be read.
+    let log_file = query.get("log_file").map(String::as_str).unwrap_or("latest.log");
+    if Path::new(log_file).file_name() != Some(log_file.as_ref()) {
+        return Ok(HttpResponse::BadRequest().json(json!({"error":"Invalid log file"})));
+    }
+    let path = server.directory.join("logs").join(log_file);
+
+    // Log files can be large, so keep the parsing off the async workers.
+    let events = web::block(move || parse_log_file(path).map_err(|e| e.to_string())).await??;
+    Ok(HttpResponse::Ok().json(events))
+}
+
 #[get("/console/sse")]
 pub async fn get_server_console_sse(
     id: web::Path<String>,
@@ -403,20 +1040,227 @@ pub async fn get_server_console_sse(
 ) -> Result<impl Responder, Box<dyn Error>> {
     let query = query.0;
     let log_file = query.get("log_file").cloned().unwrap_or_default();
+    let (sender, receiver) = tokio::sync::mpsc::channel(100);
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+
+        // Without a log file, stream the live console of the server, including what it writes to stderr.
+        // The scrollback is sent first, and the stream keeps going across restarts of the server.
+        if log_file.is_empty() {
+            let (scrollback, mut lines) = console_hub(server.id).subscribe();
+            actix_web::rt::spawn(async move {
+                for line in scrollback {
+                    let msg = sse::Data::new(serde_json::to_string(&line).unwrap_or_default()).event("console_line");
+                    if sender.send(msg.into()).await.is_err() {
+                        return;
+                    }
+                }
+                loop {
+                    // A quiet server sends no lines, so a client that left is noticed without waiting for one.
+                    let line = tokio::select! {
+                        _ = sender.closed() => break,
+                        line = lines.recv() => match line {
+                            Ok(line) => line,
+                            // A slow client misses the lines it fell behind on, but keeps receiving new ones.
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => break,
+                        },
+                    };
+                    let msg = sse::Data::new(serde_json::to_string(&line).unwrap_or_default()).event("console_line");
+                    if sender.send(msg.into()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        } else {
+            // A log file is history, the live console comes from the hub above, so the file is read once without
+            // watching it. The stream stays open until the client leaves, so it doesn't reconnect and read it again.
+            actix_web::rt::spawn(async move {
+                let read = web::block(move || server.read_log_file(log_file, |_| false).map_err(|e| e.to_string()))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|read| read);
+                match read {
+                    Ok(contents) => {
+                        if sender
+                            .send(sse::Data::new(contents).event("update_console").into())
+                            .await
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                    Err(e) => error!("Failed to read log file: {}", e),
+                }
+                sender.closed().await;
+            });
+        }
+    }
+    Ok(sse::Sse::from_infallible_receiver(receiver).with_keep_alive(Duration::from_secs(3)))
+}
+
+/// How often the console WebSocket pings the client.
+const CONSOLE_WS_PING_INTERVAL: Duration = Duration::from_secs(15);
+/// How long the console WebSocket waits for a pong before it considers the client gone.
+const CONSOLE_WS_CLIENT_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// A frame sent to the client of the console WebSocket.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ConsoleServerFrame {
+    /// A line printed by the server, with its source and timestamp.
+    Line(ConsoleLine),
+    /// A command was written to the server's console input.
+    CommandSent { command: String },
+    /// A frame from the client couldn't be handled.
+    Error { message: String },
+}
+
+/// A frame received from the client of the console WebSocket.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ConsoleClientFrame {
+    /// Writes a command to the server's console input.
+    Command { command: String },
+}
+
+#[get("/console/ws")]
+pub async fn get_server_console_ws(
+    id: web::Path<String>,
+    req: HttpRequest,
+    stream: web::Payload,
+) -> Result<HttpResponse, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the handshake below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let server = Server::get_owned_server_from_string(id.as_ref(), user_id)?;
+
+    let (response, mut session, stream) = actix_ws::handle(&req, stream)?;
+    let mut stream = stream
+        .aggregate_continuations()
+        // aggregate continuation frames up to 1MiB
+        .max_continuation_size(2_usize.pow(20));
+    let (scrollback, mut lines) = console_hub(server.id).subscribe();
+
+    actix_web::rt::spawn(async move {
+        for line in scrollback {
+            let frame = ConsoleServerFrame::Line(line);
+            if send_console_frame(&mut session, &frame).await.is_err() {
+                return;
+            }
+        }
+
+        let mut ping = interval(CONSOLE_WS_PING_INTERVAL);
+        let mut last_pong = Instant::now();
+        let reason = loop {
+            tokio::select! {
+                line = lines.recv() => match line {
+                    Ok(line) => {
+                        let frame = ConsoleServerFrame::Line(line);
+                        if send_console_frame(&mut session, &frame).await.is_err() {
+                            return;
+                        }
+                    }
+                    // A slow client misses the lines it fell behind on, but keeps receiving new ones.
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break None,
+                },
+                message = stream.next() => match message {
+                    Some(Ok(AggregatedMessage::Text(text))) => {
+                        let frame = match serde_json::from_str::<ConsoleClientFrame>(&text) {
+                            Ok(ConsoleClientFrame::Command { command }) => {
+                                let server = server.clone();
+                                let sent = command.clone();
+                                // Writing to the console input can block, so keep it off the async workers.
+                                let result = web::block(move || {
+                                    server.send_command_to_server(sent).map_err(|e| e.to_string())
+                                });
+                                match result.await {
+                                    Ok(Ok(())) => ConsoleServerFrame::CommandSent { command },
+                                    Ok(Err(message)) => ConsoleServerFrame::Error { message },
+                                    Err(e) => ConsoleServerFrame::Error { message: e.to_string() },
+                                }
+                            }
+                            Err(e) => ConsoleServerFrame::Error {
+                                message: format!("Invalid frame: {}", e),
+                            },
+                        };
+                        if send_console_frame(&mut session, &frame).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(AggregatedMessage::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(AggregatedMessage::Pong(_))) => last_pong = Instant::now(),
+                    Some(Ok(AggregatedMessage::Close(reason))) => break reason,
+                    Some(Ok(AggregatedMessage::Binary(_))) => {}
+                    Some(Err(_)) | None => break None,
+                },
+                _ = ping.tick() => {
+                    if last_pong.elapsed() > CONSOLE_WS_CLIENT_TIMEOUT {
+                        debug!("Console WebSocket client of server {} timed out", server.id);
+                        break None;
+                    }
+                    if session.ping(b"").await.is_err() {
+                        return;
+                    }
+                }
+            }
+        };
+        let _ = session.close(reason).await;
+    });
+
+    Ok(response)
+}
+
+/// Serializes a frame and sends it to the client of the console WebSocket.
+async fn send_console_frame(
+    session: &mut actix_ws::Session,
+    frame: &ConsoleServerFrame,
+) -> Result<(), actix_ws::Closed> {
+    session.text(serde_json::to_string(frame).unwrap_or_default()).await
+}
+
+#[get("/usage")]
+pub async fn get_server_usage(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    // Copy the user id out so the request extensions aren't borrowed across the await below.
+    let user_id = match req.extensions().get::<User>() {
+        Some(user) => user.id as u64,
+        None => return Ok(HttpResponse::Unauthorized().finish()),
+    };
+    let server = Server::get_owned_server_from_string(id.as_ref(), user_id)?;
+
+    // Measuring the CPU usage takes two samples, so keep the wait off the async workers.
+    let usage = web::block(move || server_usage::get_server_usage(server.id)).await?;
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+#[get("/usage/sse")]
+pub async fn get_server_usage_sse(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
     let (sender, receiver) = tokio::sync::mpsc::channel(2);
+
     if let Some(user) = req.extensions().get::<User>() {
         let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
 
         actix_web::rt::spawn(async move {
-            server.read_log_file(log_file, move |line| {
-                let msg = sse::Data::new(line).event("update_console");
-                info!("Sending message: {}", line);
-                if sender.try_send(msg.into()).is_err() {
-                    return false;
-                }
+            let mut monitor = UsageMonitor::new();
+            let mut ticker = interval(Duration::from_secs(1));
+            loop {
+                // Wait for the next tick, the first one completes right away
+                ticker.tick().await;
 
-                true
-            })
+                // The usage is null while the server isn't running
+                let usage = monitor.sample(server.id);
+                let msg = sse::Data::new(serde_json::to_string(&usage).unwrap_or_default()).event("server_usage");
+                if sender.send(msg.into()).await.is_err() {
+                    break;
+                }
+            }
         });
     }
     Ok(sse::Sse::from_infallible_receiver(receiver).with_keep_alive(Duration::from_secs(3)))
@@ -0,0 +1,124 @@
+use actix_web::{delete, get, post, put, web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use authentication::data::User;
+use serde::Deserialize;
+use serde_json::json;
+use servers::server::Server;
+use servers::server_database::ServerDatabase;
+use servers::server_schedules::{
+    create_schedule, delete_schedule, get_schedule, get_schedules, get_task_history, run_schedule_now, update_schedule,
+    ScheduleRequest, TASK_HISTORY_LIMIT,
+};
+use std::error::Error;
+
+#[get("")]
+pub async fn get_server_schedules(id: web::Path<String>, req: HttpRequest) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        return Ok(HttpResponse::Ok().json(get_schedules(server.id)?));
+    }
+    Ok(HttpResponse::Unauthorized().json(json!({"error":"Unauthorized"})))
+}
+
+#[post("")]
+pub async fn create_server_schedule(
+    id: web::Path<String>,
+    body: web::Json<ScheduleRequest>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        if let Err(e) = body.validate() {
+            return Ok(HttpResponse::BadRequest().json(json!({ "error": e })));
+        }
+        return Ok(HttpResponse::Ok().json(create_schedule(server.id, &body)?));
+    }
+    Ok(HttpResponse::Unauthorized().json(json!({"error":"Unauthorized"})))
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    schedule: Option<u64>,
+    limit: Option<u64>,
+}
+
+#[get("/history")]
+pub async fn get_server_task_history(
+    id: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let server = Server::get_owned_server_from_string(id.as_ref(), user.id as u64)?;
+        let limit = query.limit.unwrap_or(50).min(TASK_HISTORY_LIMIT);
+        return Ok(HttpResponse::Ok().json(get_task_history(server.id, query.schedule, limit)?));
+    }
+    Ok(HttpResponse::Unauthorized().json(json!({"error":"Unauthorized"})))
+}
+
+#[get("/{schedule}")]
+pub async fn get_server_schedule(
+    path: web::Path<(String, u64)>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let (id, schedule_id) = path.into_inner();
+        let server = Server::get_owned_server_from_string(&id, user.id as u64)?;
+        return Ok(match get_schedule(server.id, schedule_id)? {
+            Some(schedule) => HttpResponse::Ok().json(schedule),
+            None => HttpResponse::NotFound().json(json!({"error":"Schedule not found"})),
+        });
+    }
+    Ok(HttpResponse::Unauthorized().json(json!({"error":"Unauthorized"})))
+}
+
+#[put("/{schedule}")]
+pub async fn update_server_schedule(
+    path: web::Path<(String, u64)>,
+    body: web::Json<ScheduleRequest>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let (id, schedule_id) = path.into_inner();
+        let server = Server::get_owned_server_from_string(&id, user.id as u64)?;
+        if let Err(e) = body.validate() {
+            return Ok(HttpResponse::BadRequest().json(json!({ "error": e })));
+        }
+        return Ok(match update_schedule(server.id, schedule_id, &body)? {
+            Some(schedule) => HttpResponse::Ok().json(schedule),
+            None => HttpResponse::NotFound().json(json!({"error":"Schedule not found"})),
+        });
+    }
+    Ok(HttpResponse::Unauthorized().json(json!({"error":"Unauthorized"})))
+}
+
+#[delete("/{schedule}")]
+pub async fn delete_server_schedule(
+    path: web::Path<(String, u64)>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let (id, schedule_id) = path.into_inner();
+        let server = Server::get_owned_server_from_string(&id, user.id as u64)?;
+        if !delete_schedule(server.id, schedule_id)? {
+            return Ok(HttpResponse::NotFound().json(json!({"error":"Schedule not found"})));
+        }
+        return Ok(HttpResponse::Ok().finish());
+    }
+    Ok(HttpResponse::Unauthorized().json(json!({"error":"Unauthorized"})))
+}
+
+#[post("/{schedule}/run")]
+pub async fn run_server_schedule(
+    path: web::Path<(String, u64)>,
+    req: HttpRequest,
+) -> Result<impl Responder, Box<dyn Error>> {
+    if let Some(user) = req.extensions().get::<User>() {
+        let (id, schedule_id) = path.into_inner();
+        let server = Server::get_owned_server_from_string(&id, user.id as u64)?;
+        if !run_schedule_now(server.id, schedule_id)? {
+            return Ok(HttpResponse::NotFound().json(json!({"error":"Schedule not found"})));
+        }
+        return Ok(HttpResponse::Accepted().finish());
+    }
+    Ok(HttpResponse::Unauthorized().json(json!({"error":"Unauthorized"})))
+}
@@ -0,0 +1,107 @@
+use crate::server::Server;
+use crate::server_database::ServerDatabase;
+use crate::server_process::{is_running, ServerProcess};
+use crypto::hashids::encode;
+use lazy_static::lazy_static;
+use log::{error, info};
+use serde_derive::Serialize;
+use std::error::Error;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// The outcome of starting a single server while the panel boots.
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoStartResult {
+    /// The hashed ID of the server.
+    pub server_id: String,
+    /// The name of the server.
+    pub name: String,
+    /// The owner of the server, used to filter the report per user.
+    #[serde(skip)]
+    pub owner: u64,
+    /// The members of the server, used to filter the report per user.
+    #[serde(skip)]
+    pub members: Vec<u64>,
+    /// The auto-start order of the server.
+    pub order: u32,
+    /// The process id of the started server, `None` if it failed to start.
+    pub pid: Option<u64>,
+    /// The reason the server failed to start, if it did.
+    pub error: Option<String>,
+}
+
+lazy_static! {
+    /// The results of the last auto-start run, in the order the servers were started.
+    static ref AUTO_START_REPORT: Mutex<Vec<AutoStartResult>> = Mutex::new(Vec::new());
+}
+
+/// Starts every server flagged with `auto_start`, ordered by `auto_start_order` (lower first).
+///
+/// Servers are started one after the other, waiting `stagger` between each of them so that
+/// a proxy with a higher order can come up after its backends. A server failing to start is
+/// logged and recorded in the report, and does not prevent the remaining servers from starting.
+///
+/// # Arguments
+///
+/// * `stagger` - The delay between starting two consecutive servers.
+///
+/// # Returns
+///
+/// * `Result<Vec<AutoStartResult>, Box<dyn Error>>` - The outcome of each server, or an error if the servers couldn't be listed.
+pub fn start_auto_start_servers(stagger: Duration) -> Result<Vec<AutoStartResult>, Box<dyn Error>> {
+    let mut servers: Vec<Server<u64>> = Server::get_list_of_servers()?
+        .into_iter()
+        // Servers adopted after a panel restart are already running
+        .filter(|server| server.auto_start && !is_running(server.id))
+        .collect();
+    // The sort is stable, so servers with the same order start in the order they were created
+    servers.sort_by_key(|server| server.auto_start_order);
+
+    let mut results = Vec::new();
+    for (index, mut server) in servers.into_iter().enumerate() {
+        if index > 0 && !stagger.is_zero() {
+            thread::sleep(stagger);
+        }
+
+        let result = server.start_server();
+        match &result {
+            Ok(pid) => info!("Auto-started server {:?} with pid {}", server.name, pid),
+            Err(e) => error!("Failed to auto-start server {:?}: {}", server.name, e),
+        }
+
+        let result = AutoStartResult {
+            server_id: encode(&[server.id]),
+            name: server.name.clone(),
+            owner: server.owner,
+            members: server.members.clone(),
+            order: server.auto_start_order,
+            pid: result.as_ref().ok().copied(),
+            error: result.err().map(|e| e.to_string()),
+        };
+        if let Ok(mut report) = AUTO_START_REPORT.lock() {
+            report.push(result.clone());
+        }
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+/// Retrieves the results of the last auto-start run for the servers a user owns or is a member of.
+///
+/// # Arguments
+///
+/// * `owner_or_member` - The user ID of the owner or a member.
+pub fn get_auto_start_report(owner_or_member: u64) -> Vec<AutoStartResult> {
+    AUTO_START_REPORT
+        .lock()
+        .map(|report| {
+            report
+                .iter()
+                .filter(|result| result.owner == owner_or_member || result.members.contains(&owner_or_member))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,170 @@
+use crate::resource_limits::ResourceLimits;
+use serde_derive::Serialize;
+use std::error::Error;
+use std::path::PathBuf;
+
+/// The mount point of the cgroup v2 hierarchy.
+#[cfg(target_os = "linux")]
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+/// The cgroup, relative to the root of the hierarchy, holding the cgroup of each server.
+#[cfg(target_os = "linux")]
+const CGROUP_PARENT: &str = "obsidian";
+/// The controllers the server cgroups need.
+#[cfg(target_os = "linux")]
+const CONTROLLERS: [&str; 3] = ["cpu", "memory", "pids"];
+/// The period the CPU quota is measured over, in microseconds.
+#[cfg(target_os = "linux")]
+const CPU_PERIOD_MICROSECONDS: u64 = 100_000;
+
+/// The resources currently used by a server, read from its cgroup.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CgroupUsage {
+    /// The total CPU time used by the server since it started, in microseconds.
+    pub cpu_usage_microseconds: u64,
+    /// The memory currently used by the server in bytes, including the page cache.
+    pub memory_bytes: u64,
+    /// The highest memory usage of the server in bytes, if the kernel reports it.
+    pub memory_peak_bytes: Option<u64>,
+    /// The number of processes and threads of the server.
+    pub pids: u64,
+    /// How many times a process of the server was killed for going over the memory limit.
+    pub oom_kills: u64,
+}
+
+/// Returns the path of a server's cgroup.
+#[cfg(target_os = "linux")]
+fn server_cgroup(server_id: u64) -> PathBuf {
+    PathBuf::from(CGROUP_ROOT)
+        .join(CGROUP_PARENT)
+        .join(format!("server-{}", server_id))
+}
+
+/// Creates the cgroup of a server and writes its limits, replacing the limits of a previous run.
+///
+/// # Arguments
+/// * `server_id` - The ID of the server.
+/// * `limits` - The limits to enforce, unset limits are written as `max`.
+///
+/// # Returns
+/// The path of the cgroup, the server process moves itself into it when it starts.
+#[cfg(target_os = "linux")]
+pub(crate) fn prepare_server_cgroup(server_id: u64, limits: &ResourceLimits) -> Result<PathBuf, Box<dyn Error>> {
+    let root = PathBuf::from(CGROUP_ROOT);
+    if !root.join("cgroup.controllers").exists() {
+        return Err("cgroup v2 is not mounted at /sys/fs/cgroup".into());
+    }
+
+    // Controllers have to be enabled in every ancestor of the cgroup that uses them.
+    let parent = root.join(CGROUP_PARENT);
+    enable_controllers(&root)?;
+    std::fs::create_dir_all(&parent)?;
+    enable_controllers(&parent)?;
+
+    let cgroup = server_cgroup(server_id);
+    std::fs::create_dir_all(&cgroup)?;
+
+    let cpu_weight = limits.cpu_weight.unwrap_or(100).to_string();
+    let cpu_max = match limits.cpu_quota_percent {
+        Some(percent) => format!(
+            "{} {}",
+            percent as u64 * CPU_PERIOD_MICROSECONDS / 100,
+            CPU_PERIOD_MICROSECONDS
+        ),
+        None => format!("max {}", CPU_PERIOD_MICROSECONDS),
+    };
+    // A limit too large to count in bytes can't be reached, so it doesn't restrict the server.
+    let memory_max = limits
+        .memory_max_mb
+        .and_then(|mb| mb.checked_mul(1024 * 1024))
+        .map(|bytes| bytes.to_string())
+        .unwrap_or_else(|| "max".to_string());
+    let pids_max = limits
+        .pids_max
+        .map(|pids| pids.to_string())
+        .unwrap_or_else(|| "max".to_string());
+
+    for (file, value) in [
+        ("cpu.weight", cpu_weight),
+        ("cpu.max", cpu_max),
+        ("memory.max", memory_max),
+        ("pids.max", pids_max),
+    ] {
+        std::fs::write(cgroup.join(file), value).map_err(|e| format!("Failed to write {}: {}", file, e))?;
+    }
+    Ok(cgroup)
+}
+
+/// Cgroups are only available on Linux.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn prepare_server_cgroup(_server_id: u64, _limits: &ResourceLimits) -> Result<PathBuf, Box<dyn Error>> {
+    Err("Resource limits are only supported on Linux".into())
+}
+
+/// Enables the controllers the server cgroups need for the children of a cgroup.
+#[cfg(target_os = "linux")]
+fn enable_controllers(cgroup: &std::path::Path) -> Result<(), Box<dyn Error>> {
+    let subtree_control = cgroup.join("cgroup.subtree_control");
+    let enabled = std::fs::read_to_string(&subtree_control)?;
+    let missing: Vec<String> = CONTROLLERS
+        .iter()
+        .filter(|controller| !enabled.split_whitespace().any(|enabled| enabled == **controller))
+        .map(|controller| format!("+{}", controller))
+        .collect();
+    if !missing.is_empty() {
+        std::fs::write(&subtree_control, missing.join(" "))
+            .map_err(|e| format!("Failed to enable the {} controllers: {}", missing.join(" "), e))?;
+    }
+    Ok(())
+}
+
+/// Reads the resource usage of a server from its cgroup.
+///
+/// # Returns
+/// `None` if the server has no cgroup, e.g. because cgroups aren't available.
+#[cfg(target_os = "linux")]
+pub fn read_cgroup_usage(server_id: u64) -> Option<CgroupUsage> {
+    let cgroup = server_cgroup(server_id);
+    let read_value = |file: &str| {
+        std::fs::read_to_string(cgroup.join(file))
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+    // Reads a key from a flat keyed file like `cpu.stat`.
+    let read_key = |file: &str, key: &str| {
+        std::fs::read_to_string(cgroup.join(file)).ok().and_then(|contents| {
+            contents.lines().find_map(|line| {
+                let (name, value) = line.split_once(' ')?;
+                (name == key).then(|| value.trim().parse::<u64>().ok()).flatten()
+            })
+        })
+    };
+
+    Some(CgroupUsage {
+        cpu_usage_microseconds: read_key("cpu.stat", "usage_usec")?,
+        memory_bytes: read_value("memory.current")?,
+        memory_peak_bytes: read_value("memory.peak"),
+        pids: read_value("pids.current")?,
+        oom_kills: read_key("memory.events", "oom_kill").unwrap_or_default(),
+    })
+}
+
+/// Cgroups are only available on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn read_cgroup_usage(_server_id: u64) -> Option<CgroupUsage> {
+    None
+}
+
+/// Removes the cgroup of a server after its process exited, which only succeeds once it's empty.
+pub(crate) fn remove_server_cgroup(server_id: u64) {
+    #[cfg(target_os = "linux")]
+    {
+        let cgroup = server_cgroup(server_id);
+        if cgroup.exists() {
+            if let Err(e) = std::fs::remove_dir(&cgroup) {
+                log::debug!("Failed to remove cgroup {:?}: {}", cgroup, e);
+            }
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = server_id;
+}
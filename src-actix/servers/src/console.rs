@@ -0,0 +1,193 @@
+use crate::log_events::{LogEvent, LogEventParser};
+use lazy_static::lazy_static;
+use serde_derive::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+/// The stream a console line was written to by the server process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsoleSource {
+    /// The standard output stream.
+    Stdout,
+    /// The standard error stream, where Java writes stack traces and fatal errors.
+    Stderr,
+}
+
+/// A single line printed by a server process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConsoleLine {
+    /// The stream the line was written to.
+    pub source: ConsoleSource,
+    /// The text of the line, without the trailing newline.
+    pub line: String,
+    /// When the panel read the line, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl ConsoleLine {
+    /// Creates a new console line, timestamped with the current time.
+    pub fn new(source: ConsoleSource, line: impl Into<String>) -> Self {
+        Self {
+            source,
+            line: line.into(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Display for ConsoleLine {
+    /// Formats the line as plain text, prefixing lines written to stderr so they stand out in stored output.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.source {
+            ConsoleSource::Stdout => write!(f, "{}", self.line),
+            ConsoleSource::Stderr => write!(f, "[stderr] {}", self.line),
+        }
+    }
+}
+
+/// The console lines printed in response to a command.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CommandCapture {
+    /// The lines printed after the command was sent, oldest first.
+    pub lines: Vec<ConsoleLine>,
+    /// Whether a line matched the pattern the capture was waiting for, `false` if the window ran out.
+    pub matched: bool,
+}
+
+/// The number of recent console lines each server keeps for new subscribers.
+pub const CONSOLE_SCROLLBACK_LINES: usize = 1000;
+/// The number of lines a subscriber can fall behind before it starts missing lines.
+const CONSOLE_CHANNEL_CAPACITY: usize = 1024;
+
+lazy_static! {
+    /// The console hub of every server that has been started since the panel booted.
+    static ref CONSOLE_HUBS: Mutex<HashMap<u64, Arc<ConsoleHub>>> = Mutex::new(HashMap::new());
+}
+
+/// Broadcasts the console of a server to any number of subscribers, keeping a bounded
+/// scrollback of the most recent lines so new subscribers can catch up.
+///
+/// The standard output is also parsed into log events, which are broadcast on their own channel.
+/// The hub outlives the server process, so subscribers keep receiving lines across restarts.
+pub struct ConsoleHub {
+    /// The most recent lines, oldest first.
+    scrollback: Mutex<VecDeque<ConsoleLine>>,
+    /// The channel new lines are sent to.
+    sender: broadcast::Sender<ConsoleLine>,
+    /// Turns the standard output of the current run into log events.
+    parser: Mutex<LogEventParser>,
+    /// The channel log events are sent to.
+    events: broadcast::Sender<LogEvent>,
+    /// Set while the console log of an adopted server is read back, its events are marked as replayed.
+    replaying: AtomicBool,
+}
+
+impl ConsoleHub {
+    fn new() -> Self {
+        let (sender, _) = broadcast::channel(CONSOLE_CHANNEL_CAPACITY);
+        let (events, _) = broadcast::channel(CONSOLE_CHANNEL_CAPACITY);
+        Self {
+            scrollback: Mutex::new(VecDeque::with_capacity(CONSOLE_SCROLLBACK_LINES)),
+            sender,
+            parser: Mutex::new(LogEventParser::new()),
+            events,
+            replaying: AtomicBool::new(false),
+        }
+    }
+
+    /// Adds a line to the scrollback and sends it to every subscriber.
+    pub fn publish(&self, line: ConsoleLine) {
+        let events = match (line.source, self.parser.lock()) {
+            (ConsoleSource::Stdout, Ok(mut parser)) => parser.parse(&line.line, line.timestamp),
+            _ => Vec::new(),
+        };
+        if let Ok(mut scrollback) = self.scrollback.lock() {
+            if scrollback.len() == CONSOLE_SCROLLBACK_LINES {
+                scrollback.pop_front();
+            }
+            scrollback.push_back(line.clone());
+            // Sending while holding the scrollback keeps it in sync with `subscribe`, it only fails without subscribers.
+            let _ = self.sender.send(line);
+        }
+        let replayed = self.is_replaying();
+        for mut event in events {
+            event.replayed = replayed;
+            let _ = self.events.send(event);
+        }
+    }
+
+    /// Marks the lines published from now on as read back from the console log, until `finish_replay`.
+    pub(crate) fn start_replay(&self) {
+        self.replaying.store(true, Ordering::SeqCst);
+    }
+
+    /// Marks the lines published from now on as live again.
+    pub(crate) fn finish_replay(&self) {
+        self.replaying.store(false, Ordering::SeqCst);
+    }
+
+    /// Checks if the console log of an adopted server is still being read back.
+    pub(crate) fn is_replaying(&self) -> bool {
+        self.replaying.load(Ordering::SeqCst)
+    }
+
+    /// Subscribes to the console.
+    ///
+    /// # Returns
+    /// The scrollback at the time of subscribing, and a receiver for every line published afterward.
+    pub fn subscribe(&self) -> (Vec<ConsoleLine>, broadcast::Receiver<ConsoleLine>) {
+        match self.scrollback.lock() {
+            Ok(scrollback) => (scrollback.iter().cloned().collect(), self.sender.subscribe()),
+            Err(_) => (Vec::new(), self.sender.subscribe()),
+        }
+    }
+
+    /// Subscribes to the log events parsed from the standard output.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<LogEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns up to `count` of the most recent lines, oldest first.
+    pub fn recent(&self, count: usize) -> Vec<ConsoleLine> {
+        self.scrollback
+            .lock()
+            .map(|scrollback| {
+                scrollback
+                    .iter()
+                    .skip(scrollback.len().saturating_sub(count))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Empties the scrollback and forgets the players of the previous run, used when a new run of the server starts.
+    pub fn clear(&self) {
+        if let Ok(mut scrollback) = self.scrollback.lock() {
+            scrollback.clear();
+        }
+        if let Ok(mut parser) = self.parser.lock() {
+            *parser = LogEventParser::new();
+        }
+    }
+}
+
+/// Returns the console hub of a server, creating it if it doesn't exist yet.
+///
+/// # Arguments
+/// * `server_id` - The ID of the server.
+pub fn console_hub(server_id: u64) -> Arc<ConsoleHub> {
+    match CONSOLE_HUBS.lock() {
+        Ok(mut hubs) => Arc::clone(hubs.entry(server_id).or_insert_with(|| Arc::new(ConsoleHub::new()))),
+        // A poisoned map only loses the sharing, the console keeps working for this caller.
+        Err(_) => Arc::new(ConsoleHub::new()),
+    }
+}
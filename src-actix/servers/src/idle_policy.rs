@@ -0,0 +1,140 @@
+use crate::server::Server;
+use crate::server_database::ServerDatabase;
+use crate::server_players::{online_players, refresh_players};
+use crate::server_process::ServerProcess;
+use crate::server_status::ServerStatus;
+use lazy_static::lazy_static;
+use log::{info, warn};
+use scheduler::duration::Duration;
+use scheduler::schedule_manager::SCHEDULE_MANAGER_SINGLETON;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// How often the running servers are checked for players, in seconds.
+const IDLE_CHECK_INTERVAL_SECONDS: u64 = 30;
+
+lazy_static! {
+    /// When each online server was first seen without players, by server id.
+    static ref EMPTY_SINCE: Mutex<HashMap<u64, Instant>> = Mutex::new(HashMap::new());
+}
+
+/// Per-server policy for stopping a server that has been empty for a while.
+///
+/// A server stopped by the policy is `Sleeping`. If `wake_on_connect` is set, the panel listens
+/// on the server's port while it sleeps, answers pings with `motd` and starts the server when
+/// a player tries to join, disconnecting them with `kick_message`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdlePolicy {
+    /// Whether the server is stopped when it's idle.
+    pub enabled: bool,
+    /// How long the server has to be without players before it's stopped, in minutes.
+    pub idle_minutes: u64,
+    /// Whether the server is started again when a player tries to join.
+    pub wake_on_connect: bool,
+    /// The MOTD shown in the server list while the server sleeps.
+    pub motd: String,
+    /// The message a player trying to join is disconnected with while the server starts.
+    pub kick_message: String,
+}
+
+impl Default for IdlePolicy {
+    /// Provides the default policy, which never stops the server.
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_minutes: 15,
+            wake_on_connect: true,
+            motd: "\u{a7}7Sleeping, join to wake the server".to_string(),
+            kick_message: "The server is starting, join again in a minute".to_string(),
+        }
+    }
+}
+
+/// Checks the online servers for players every `IDLE_CHECK_INTERVAL_SECONDS`, putting the ones
+/// that have been empty for longer than their idle policy allows to sleep.
+pub fn start_idle_monitor() -> Result<(), Box<dyn Error>> {
+    SCHEDULE_MANAGER_SINGLETON
+        .lock()
+        .map_err(|_| "Failed to lock the schedule manager")?
+        .add_schedule(
+            Duration::from_seconds(IDLE_CHECK_INTERVAL_SECONDS),
+            true,
+            false,
+            // The manager is locked while it ticks, and stopping a server takes a while.
+            |_| {
+                thread::spawn(|| {
+                    if let Err(e) = check_idle_servers() {
+                        warn!("Failed to check for idle servers: {}", e);
+                    }
+                });
+            },
+        );
+    Ok(())
+}
+
+/// Puts every online server to sleep that has been empty for longer than its idle policy allows.
+fn check_idle_servers() -> Result<(), Box<dyn Error>> {
+    let mut idle = Vec::new();
+    {
+        let mut empty_since = EMPTY_SINCE.lock().map_err(|_| "Failed to lock the idle timers")?;
+        let servers = Server::get_list_of_servers()?;
+        // Forget the servers that were removed, stopped or got players in the meantime.
+        empty_since.retain(|id, _| {
+            servers.iter().any(|server| {
+                server.id == *id
+                    && server.idle_policy.enabled
+                    && server.status == Some(ServerStatus::Online)
+                    && online_players(server.id).is_empty()
+            })
+        });
+
+        for server in servers {
+            if !server.idle_policy.enabled
+                || server.status != Some(ServerStatus::Online)
+                || !online_players(server.id).is_empty()
+            {
+                continue;
+            }
+            let since = *empty_since.entry(server.id).or_insert_with(Instant::now);
+            if since.elapsed().as_secs() >= server.idle_policy.idle_minutes * 60 {
+                empty_since.remove(&server.id);
+                idle.push(server);
+            }
+        }
+    }
+
+    for mut server in idle {
+        // The roster can miss a join, so the server is asked for its players before it's stopped.
+        match refresh_players(&server) {
+            Ok(players) if players.is_empty() => {}
+            Ok(players) => {
+                info!(
+                    "Server {:?} still has {} players online, it's not put to sleep",
+                    server.name,
+                    players.len()
+                );
+                continue;
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to list the players of server {:?}, it's not put to sleep: {}",
+                    server.name, e
+                );
+                continue;
+            }
+        }
+        info!(
+            "Server {:?} has been empty for {} minutes, putting it to sleep",
+            server.name, server.idle_policy.idle_minutes
+        );
+        if let Err(e) = server.sleep_server() {
+            warn!("Failed to put server {:?} to sleep: {}", server.name, e);
+        }
+    }
+    Ok(())
+}
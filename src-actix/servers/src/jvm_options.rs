@@ -0,0 +1,320 @@
+use serde_derive::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+/// The heap from which Aikar's flags switch to larger young generation and region sizes, in MB.
+const AIKAR_LARGE_HEAP_MB: u64 = 12 * 1024;
+
+/// Flags that the JVM refuses to start with from the given Java version on, because they were removed.
+pub const REMOVED_FLAGS: &[(&str, u32)] = &[
+    ("-XX:MaxPermSize", 17),
+    ("-XX:PermSize", 17),
+    ("-XX:+UseParNewGC", 10),
+    ("-XX:+AggressiveOpts", 12),
+    ("-XX:+UseConcMarkSweepGC", 14),
+    ("-XX:+CMSClassUnloadingEnabled", 14),
+    ("-XX:+CMSIncrementalMode", 10),
+];
+
+/// A garbage collector the JVM can be started with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GarbageCollector {
+    G1,
+    Zgc,
+    /// ZGC with separate young and old generations, the only ZGC mode from Java 24 on.
+    GenerationalZgc,
+    /// Only included in some builds of OpenJDK, e.g. not in Oracle's.
+    Shenandoah,
+    Parallel,
+    Serial,
+}
+
+/// A named set of JVM flags tuned for Minecraft servers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JvmPreset {
+    /// No tuning, only the garbage collector and the raw arguments are passed.
+    #[default]
+    None,
+    /// Aikar's G1 flags, https://docs.papermc.io/paper/aikars-flags.
+    Aikar,
+    /// Generational ZGC, for large heaps on Java 21 and newer.
+    Zgc,
+}
+
+/// The structured JVM options of a server.
+///
+/// The heap is taken from the server's `min_ram` and `max_ram`, and the server's `java_arguments`
+/// are appended as extra raw arguments, so they can override any flag set here.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JvmOptions {
+    /// The named set of flags to start with.
+    pub preset: JvmPreset,
+    /// The garbage collector, for servers without a preset, which picks its own.
+    pub gc: Option<GarbageCollector>,
+}
+
+impl GarbageCollector {
+    /// The first Java version in which the collector is production ready.
+    pub fn min_java_version(&self) -> u32 {
+        match self {
+            GarbageCollector::G1 | GarbageCollector::Parallel | GarbageCollector::Serial => 8,
+            GarbageCollector::Shenandoah => 12,
+            GarbageCollector::Zgc => 15,
+            GarbageCollector::GenerationalZgc => 21,
+        }
+    }
+
+    /// The flags that select the collector.
+    ///
+    /// # Arguments
+    /// * `java_version` - The major version of the Java runtime, if known.
+    pub fn flags(&self, java_version: Option<u32>) -> Vec<&'static str> {
+        match self {
+            GarbageCollector::G1 => vec!["-XX:+UseG1GC"],
+            GarbageCollector::Zgc => vec!["-XX:+UseZGC"],
+            // Java 21 and 22 need the mode turned on, later versions deprecate the flag.
+            GarbageCollector::GenerationalZgc => match java_version {
+                Some(21..=22) | None => vec!["-XX:+UseZGC", "-XX:+ZGenerational"],
+                Some(_) => vec!["-XX:+UseZGC"],
+            },
+            GarbageCollector::Shenandoah => vec!["-XX:+UseShenandoahGC"],
+            GarbageCollector::Parallel => vec!["-XX:+UseParallelGC"],
+            GarbageCollector::Serial => vec!["-XX:+UseSerialGC"],
+        }
+    }
+}
+
+impl JvmPreset {
+    /// The collector the preset is tuned for, `None` for no preset.
+    pub fn gc(&self) -> Option<GarbageCollector> {
+        match self {
+            JvmPreset::None => None,
+            JvmPreset::Aikar => Some(GarbageCollector::G1),
+            JvmPreset::Zgc => Some(GarbageCollector::GenerationalZgc),
+        }
+    }
+
+    /// The flags of the preset, including the ones selecting its collector.
+    ///
+    /// # Arguments
+    /// * `max_ram` - The maximum heap in MB, which some presets scale with.
+    /// * `java_version` - The major version of the Java runtime, if known.
+    pub fn flags(&self, max_ram: u64, java_version: Option<u32>) -> Vec<String> {
+        let mut flags: Vec<String> = self
+            .gc()
+            .map(|gc| gc.flags(java_version).into_iter().map(str::to_string).collect())
+            .unwrap_or_default();
+        match self {
+            JvmPreset::None => {}
+            JvmPreset::Aikar => {
+                let large = max_ram >= AIKAR_LARGE_HEAP_MB;
+                let (new_size, max_new_size, region_size, reserve, occupancy) = if large {
+                    (40, 50, "16M", 15, 20)
+                } else {
+                    (30, 40, "8M", 20, 15)
+                };
+                flags.extend([
+                    "-XX:+ParallelRefProcEnabled".to_string(),
+                    "-XX:MaxGCPauseMillis=200".to_string(),
+                    "-XX:+UnlockExperimentalVMOptions".to_string(),
+                    "-XX:+DisableExplicitGC".to_string(),
+                    "-XX:+AlwaysPreTouch".to_string(),
+                    format!("-XX:G1NewSizePercent={}", new_size),
+                    format!("-XX:G1MaxNewSizePercent={}", max_new_size),
+                    format!("-XX:G1HeapRegionSize={}", region_size),
+                    format!("-XX:G1ReservePercent={}", reserve),
+                    "-XX:G1HeapWastePercent=5".to_string(),
+                    "-XX:G1MixedGCCountTarget=4".to_string(),
+                    format!("-XX:InitiatingHeapOccupancyPercent={}", occupancy),
+                    "-XX:G1MixedGCLiveThresholdPercent=90".to_string(),
+                    "-XX:G1RSetUpdatingPauseTimePercent=5".to_string(),
+                    "-XX:SurvivorRatio=32".to_string(),
+                    "-XX:+PerfDisableSharedMem".to_string(),
+                    "-XX:MaxTenuringThreshold=1".to_string(),
+                    "-Dusing.aikars.flags=https://mcflags.emc.gs".to_string(),
+                    "-Daikars.new.flags=true".to_string(),
+                ]);
+            }
+            JvmPreset::Zgc => flags.extend([
+                "-XX:+AlwaysPreTouch".to_string(),
+                "-XX:+DisableExplicitGC".to_string(),
+                "-XX:+PerfDisableSharedMem".to_string(),
+            ]),
+        }
+        flags
+    }
+}
+
+impl JvmOptions {
+    /// The collector the options select, through the preset or explicitly.
+    pub fn selected_gc(&self) -> Option<GarbageCollector> {
+        self.preset.gc().or(self.gc)
+    }
+
+    /// Builds the heap, preset and collector flags, in the order they're passed to Java.
+    ///
+    /// # Arguments
+    /// * `min_ram` - The initial heap in MB, not passed if `0`.
+    /// * `max_ram` - The maximum heap in MB, not passed if `0`.
+    /// * `java_version` - The major version of the Java runtime, if known.
+    pub fn build_args(&self, min_ram: u64, max_ram: u64, java_version: Option<u32>) -> Vec<String> {
+        let mut args = Vec::new();
+        if min_ram > 0 {
+            args.push(format!("-Xms{}M", min_ram));
+        }
+        if max_ram > 0 {
+            args.push(format!("-Xmx{}M", max_ram));
+        }
+        args.extend(self.build_flags(max_ram, java_version));
+        args
+    }
+
+    /// Builds the preset and collector flags without the heap, for start commands that set the heap themselves.
+    ///
+    /// # Arguments
+    /// * `max_ram` - The maximum heap in MB, which some presets tune their flags for.
+    /// * `java_version` - The major version of the Java runtime, if known.
+    pub fn build_flags(&self, max_ram: u64, java_version: Option<u32>) -> Vec<String> {
+        match self.preset {
+            JvmPreset::None => self
+                .gc
+                .map(|gc| gc.flags(java_version).into_iter().map(str::to_string).collect())
+                .unwrap_or_default(),
+            preset => preset.flags(max_ram, java_version),
+        }
+    }
+}
+
+/// Reads the major version of a Java runtime, e.g. `8` for `1.8.0_292` and `21` for `21.0.4`.
+///
+/// The `release` file of the runtime is read if there is one, otherwise `java -version` is run.
+///
+/// # Arguments
+/// * `java_runtime` - The path of the `java` executable, or a command looked up in the PATH.
+pub fn java_major_version(java_runtime: &Path) -> Option<u32> {
+    // The executable is in `bin`, next to the `release` file of the runtime.
+    let release = java_runtime
+        .parent()
+        .and_then(Path::parent)
+        .map(|home| home.join("release"))
+        .and_then(|release| std::fs::read_to_string(release).ok());
+    if let Some(version) = release.as_deref().and_then(|release| {
+        release
+            .lines()
+            .find_map(|line| line.strip_prefix("JAVA_VERSION="))
+            .and_then(|version| parse_major_version(version.trim_matches('"')))
+    }) {
+        return Some(version);
+    }
+
+    // `java -version` prints e.g. `openjdk version "21.0.4" 2024-07-16` to stderr.
+    let output = Command::new(java_runtime).arg("-version").output().ok()?;
+    let output = String::from_utf8_lossy(&output.stderr);
+    let version = output.split('"').nth(1)?;
+    parse_major_version(version)
+}
+
+/// Parses the major version out of a Java version string.
+fn parse_major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(['.', '_', '-', '+']);
+    match parts.next()? {
+        // Java 8 and older are versioned 1.x.
+        "1" => parts.next()?.parse().ok(),
+        major => major.parse().ok(),
+    }
+}
+
+impl FromStr for JvmPreset {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "" | "none" => Ok(JvmPreset::None),
+            "aikar" | "aikars" => Ok(JvmPreset::Aikar),
+            "zgc" => Ok(JvmPreset::Zgc),
+            _ => Err(()),
+        }
+    }
+}
+
+impl FromStr for GarbageCollector {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "g1" => Ok(GarbageCollector::G1),
+            "zgc" => Ok(GarbageCollector::Zgc),
+            "generational-zgc" => Ok(GarbageCollector::GenerationalZgc),
+            "shenandoah" => Ok(GarbageCollector::Shenandoah),
+            "parallel" => Ok(GarbageCollector::Parallel),
+            "serial" => Ok(GarbageCollector::Serial),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_args() {
+        let options = JvmOptions {
+            preset: JvmPreset::None,
+            gc: Some(GarbageCollector::GenerationalZgc),
+        };
+        assert_eq!(
+            options.build_args(1024, 4096, Some(21)),
+            vec!["-Xms1024M", "-Xmx4096M", "-XX:+UseZGC", "-XX:+ZGenerational"]
+        );
+        // Java 23 and newer only have generational ZGC.
+        assert_eq!(options.build_args(0, 0, Some(23)), vec!["-XX:+UseZGC"]);
+        assert!(JvmOptions::default().build_args(0, 0, None).is_empty());
+
+        // The preset picks its own collector over the one set.
+        let options = JvmOptions {
+            preset: JvmPreset::Aikar,
+            gc: Some(GarbageCollector::Parallel),
+        };
+        let args = options.build_args(0, 4096, Some(21));
+        assert_eq!(args.get(1).map(String::as_str), Some("-XX:+UseG1GC"));
+        assert!(args.contains(&"-XX:G1HeapRegionSize=8M".to_string()));
+        assert!(!args.contains(&"-XX:+UseParallelGC".to_string()));
+        // Heaps of 12 GB and more get larger regions.
+        let args = options.build_args(0, 16 * 1024, Some(21));
+        assert!(args.contains(&"-XX:G1HeapRegionSize=16M".to_string()));
+        // The flags are the arguments without the heap.
+        assert_eq!(
+            options.build_flags(4096, Some(21)),
+            options.build_args(0, 4096, Some(21))[1..]
+        );
+    }
+
+    #[test]
+    fn test_parse_major_version() {
+        assert_eq!(parse_major_version("1.8.0_292"), Some(8));
+        assert_eq!(parse_major_version("21.0.4"), Some(21));
+        assert_eq!(parse_major_version("17"), Some(17));
+        assert_eq!(parse_major_version("22-ea"), Some(22));
+        assert_eq!(parse_major_version("11.0.2+9"), Some(11));
+        assert_eq!(parse_major_version("unknown"), None);
+    }
+
+    #[test]
+    fn test_java_major_version_from_release() -> Result<(), Box<dyn std::error::Error>> {
+        let home = std::env::temp_dir().join(format!("jvm_options_test_{}", std::process::id()));
+        std::fs::create_dir_all(home.join("bin"))?;
+        std::fs::write(
+            home.join("release"),
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.12\"\n",
+        )?;
+        let version = java_major_version(&home.join("bin").join("java"));
+        std::fs::remove_dir_all(&home)?;
+        assert_eq!(version, Some(17));
+        Ok(())
+    }
+}
@@ -2,11 +2,31 @@
 #![deny(clippy::expect_used)]
 #![deny(clippy::panic)]
 #![deny(unused_must_use)]
+pub mod auto_start;
+pub mod cgroup;
+pub mod console;
 pub mod file_system_entry;
+pub mod idle_policy;
+pub mod jvm_options;
+pub mod log_events;
+pub mod rcon;
+pub mod readiness;
+pub mod resource_limits;
+pub mod restart_policy;
 pub mod server;
+pub mod server_crash;
 pub mod server_database;
+pub mod server_eula;
 pub mod server_filesystem;
+pub mod server_list_ping;
+pub mod server_players;
 pub mod server_process;
 pub mod server_properties;
+pub mod server_schedules;
+pub mod server_query;
 pub mod server_status;
+pub mod server_usage;
+pub mod start_command;
 pub mod start_executable_type;
+pub mod start_validation;
+pub mod wake_listener;
@@ -0,0 +1,557 @@
+use chrono::{Days, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use lazy_static::lazy_static;
+use log::error;
+use regex::Regex;
+use serde_derive::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The severity of a log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl FromStr for LogLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "TRACE" => Ok(LogLevel::Trace),
+            "DEBUG" => Ok(LogLevel::Debug),
+            "INFO" => Ok(LogLevel::Info),
+            "WARN" | "WARNING" => Ok(LogLevel::Warn),
+            "ERROR" | "SEVERE" => Ok(LogLevel::Error),
+            "FATAL" => Ok(LogLevel::Fatal),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What happened on the server, as recognized from a log line.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LogEventKind {
+    /// A player connected and joined the world.
+    PlayerJoined { player: String, uuid: Option<String> },
+    /// A player left the world, with the reason they lost the connection if it was logged.
+    PlayerLeft {
+        player: String,
+        uuid: Option<String>,
+        reason: Option<String>,
+    },
+    /// A player sent a chat message.
+    Chat { player: String, message: String },
+    /// A player made an advancement, completed a challenge or reached a goal.
+    Advancement { player: String, advancement: String },
+    /// A player died, `message` is the death message without the player name.
+    Death { player: String, message: String },
+    /// A player ran a command.
+    CommandIssued { player: String, command: String },
+    /// A warning or an error, with the class of the exception if the line names one.
+    Problem { message: String, exception: Option<String> },
+    /// The server finished starting.
+    ServerStarted { startup_seconds: Option<f64> },
+    /// The server began shutting down.
+    ServerStopping,
+}
+
+/// An event recognized in the log of a server.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogEvent {
+    /// When the line was logged, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The severity of the line.
+    pub level: LogLevel,
+    /// The thread that logged the line, if the log format includes it.
+    pub thread: Option<String>,
+    /// What happened.
+    #[serde(flatten)]
+    pub kind: LogEventKind,
+    /// Whether the line was read back from the console log of a server adopted after the panel restarted,
+    /// so it may already have been handled before the restart.
+    #[serde(skip)]
+    pub replayed: bool,
+}
+
+/// The parts of a log line, split off the prefix the server's logger adds to it.
+struct LogRecord<'a> {
+    /// The date, only logged by Forge.
+    date: Option<NaiveDate>,
+    time: NaiveTime,
+    thread: Option<&'a str>,
+    level: LogLevel,
+    message: &'a str,
+}
+
+/// The regular expressions the parser matches log lines against.
+struct LogPatterns {
+    /// Vanilla, Fabric and Forge: `[12:34:56] [Server thread/INFO]: ...`, where Forge adds the
+    /// date, milliseconds and the logger: `[18Oct2026 12:34:56.789] [Server thread/INFO] [minecraft/]: ...`.
+    vanilla_prefix: Regex,
+    /// Paper and Spigot: `[12:34:56 INFO]: ...`.
+    paper_prefix: Regex,
+    /// The escape sequences some servers color their console with.
+    ansi: Regex,
+    uuid: Regex,
+    joined: Regex,
+    left: Regex,
+    lost_connection: Regex,
+    chat: Regex,
+    advancement: Regex,
+    death: Regex,
+    command: Regex,
+    started: Regex,
+    stopping: Regex,
+    exception: Regex,
+    /// The first line of a stack trace, which starts with the exception class.
+    stack_trace: Regex,
+}
+
+impl LogPatterns {
+    fn new() -> Result<Self, regex::Error> {
+        Ok(Self {
+            vanilla_prefix: Regex::new(
+                r"^\[(?:(?P<date>\d{1,2}[A-Za-z]{3}\d{4}) )?(?P<time>\d{2}:\d{2}:\d{2})(?:\.\d+)?\] \[(?P<thread>[^\]]+?)/(?P<level>[A-Z]+)\](?: \[[^\]]*\])?: (?P<message>.*)$",
+            )?,
+            paper_prefix: Regex::new(r"^\[(?P<time>\d{2}:\d{2}:\d{2}) (?P<level>[A-Z]+)\]: (?P<message>.*)$")?,
+            ansi: Regex::new(r"\x1b\[[0-9;]*[A-Za-z]")?,
+            uuid: Regex::new(r"^UUID of player (?P<player>\S+) is (?P<uuid>[0-9a-fA-F-]{32,36})$")?,
+            joined: Regex::new(r"^(?P<player>\S+) joined the game$")?,
+            left: Regex::new(r"^(?P<player>\S+) left the game$")?,
+            lost_connection: Regex::new(r"^(?P<player>\S+) lost connection: (?P<reason>.*)$")?,
+            chat: Regex::new(r"^(?:\[Not Secure\] )?<(?P<player>[^>]+)> (?P<message>.*)$")?,
+            advancement: Regex::new(
+                r"^(?P<player>\S+) has (?:made the advancement|completed the challenge|reached the goal) \[(?P<advancement>.+)\]$",
+            )?,
+            death: Regex::new(
+                r"^(?P<player>[A-Za-z0-9_]{3,16}) (?P<message>(?:was|fell|drowned|died|blew up|burned|hit the ground|starved|suffocated|tried to swim|went up in flames|went off with a bang|withered away|froze to death|walked into|discovered the floor|experienced kinetic energy|didn't want to live|left the confines|is not a fan|was squished)\b.*)$",
+            )?,
+            command: Regex::new(r"^(?P<player>\S+) issued server command: (?P<command>.*)$")?,
+            started: Regex::new(r#"^Done \((?P<seconds>[\d.,]+)s\)! For help, type "help""#)?,
+            stopping: Regex::new(r"^Stopping (?:the )?server$")?,
+            exception: Regex::new(r"\b(?P<exception>(?:[a-z_$][\w$]*\.)+[A-Z][\w$]*(?:Exception|Error|Throwable))\b")?,
+            stack_trace: Regex::new(r"^(?:Caused by: )?(?P<exception>(?:[a-z_$][\w$]*\.)+[A-Z][\w$]*)(?::|$)")?,
+        })
+    }
+}
+
+lazy_static! {
+    static ref PATTERNS: Option<LogPatterns> = LogPatterns::new()
+        .map_err(|e| error!("Failed to compile the log patterns: {}", e))
+        .ok();
+}
+
+/// Turns the log lines of a server into events.
+///
+/// The parser is fed the lines of one server in order, and keeps track of the players online
+/// to attach UUIDs and disconnect reasons to their join and leave events, and to tell death
+/// messages apart from other lines. Warnings and errors are held back until the next line,
+/// which names the exception when the logger printed a stack trace.
+#[derive(Debug, Default)]
+pub struct LogEventParser {
+    /// A problem waiting for the line that may name its exception.
+    pending: Option<LogEvent>,
+    /// The UUIDs logged by the authenticator, by player name.
+    uuids: HashMap<String, String>,
+    /// The reasons of connections lost but not yet followed by the leave message, by player name.
+    disconnect_reasons: HashMap<String, String>,
+    /// The players that joined and haven't left.
+    online: HashSet<String>,
+}
+
+impl LogEventParser {
+    /// Creates a parser that doesn't know any players yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a line of the live console.
+    ///
+    /// # Arguments
+    /// * `line` - The line printed by the server.
+    /// * `timestamp` - When the line was read, in milliseconds since the Unix epoch. The events are dated by
+    ///   the time of day in the line itself, this only supplies the date.
+    ///
+    /// # Returns
+    /// The events completed by the line, usually none or one.
+    pub fn parse(&mut self, line: &str, timestamp: u64) -> Vec<LogEvent> {
+        let line = strip_ansi(line);
+        match split_line(&line) {
+            Some(record) => self.parse_record(&record, live_timestamp(&record, timestamp)),
+            None => self.parse_continuation(&line),
+        }
+    }
+
+    /// Returns the problem still held back, used once no more lines will follow.
+    pub fn flush(&mut self) -> Option<LogEvent> {
+        self.pending.take()
+    }
+
+    /// Parses a line written by the server's logger, releasing the problem held back before it.
+    fn parse_record(&mut self, record: &LogRecord, timestamp: u64) -> Vec<LogEvent> {
+        let mut events: Vec<LogEvent> = self.pending.take().into_iter().collect();
+        match self.interpret(record, timestamp) {
+            Some(event) if matches!(event.kind, LogEventKind::Problem { exception: None, .. }) => {
+                self.pending = Some(event)
+            }
+            Some(event) => events.push(event),
+            None => {}
+        }
+        events
+    }
+
+    /// Parses a line without the logger's prefix, like the first line of a stack trace.
+    fn parse_continuation(&mut self, line: &str) -> Vec<LogEvent> {
+        let Some(mut event) = self.pending.take() else {
+            return Vec::new();
+        };
+        if let (LogEventKind::Problem { exception, .. }, Some(patterns)) = (&mut event.kind, PATTERNS.as_ref()) {
+            *exception = patterns
+                .stack_trace
+                .captures(line)
+                .map(|captures| captures["exception"].to_string());
+        }
+        vec![event]
+    }
+
+    /// Recognizes the event a log record describes.
+    fn interpret(&mut self, record: &LogRecord, timestamp: u64) -> Option<LogEvent> {
+        let patterns = PATTERNS.as_ref()?;
+        let message = record.message.trim_end();
+        let player = |captures: &regex::Captures| captures["player"].to_string();
+
+        let kind = if matches!(record.level, LogLevel::Warn | LogLevel::Error | LogLevel::Fatal) {
+            LogEventKind::Problem {
+                message: message.to_string(),
+                exception: patterns
+                    .exception
+                    .captures(message)
+                    .map(|captures| captures["exception"].to_string()),
+            }
+        } else if record.level != LogLevel::Info {
+            return None;
+        } else if let Some(captures) = patterns.uuid.captures(message) {
+            // Only remembered, the UUID is reported with the join that follows.
+            self.uuids.insert(player(&captures), captures["uuid"].to_string());
+            return None;
+        } else if let Some(captures) = patterns.lost_connection.captures(message) {
+            self.disconnect_reasons
+                .insert(player(&captures), captures["reason"].to_string());
+            return None;
+        } else if let Some(captures) = patterns.joined.captures(message) {
+            let player = player(&captures);
+            self.online.insert(player.clone());
+            LogEventKind::PlayerJoined {
+                uuid: self.uuids.get(&player).cloned(),
+                player,
+            }
+        } else if let Some(captures) = patterns.left.captures(message) {
+            let player = player(&captures);
+            self.online.remove(&player);
+            LogEventKind::PlayerLeft {
+                uuid: self.uuids.get(&player).cloned(),
+                reason: self.disconnect_reasons.remove(&player),
+                player,
+            }
+        } else if let Some(captures) = patterns.chat.captures(message) {
+            LogEventKind::Chat {
+                player: player(&captures),
+                message: captures["message"].to_string(),
+            }
+        } else if let Some(captures) = patterns.advancement.captures(message) {
+            LogEventKind::Advancement {
+                player: player(&captures),
+                advancement: captures["advancement"].to_string(),
+            }
+        } else if let Some(captures) = patterns.command.captures(message) {
+            LogEventKind::CommandIssued {
+                player: player(&captures),
+                command: captures["command"].to_string(),
+            }
+        } else if let Some(captures) = patterns.started.captures(message) {
+            LogEventKind::ServerStarted {
+                startup_seconds: captures["seconds"].replace(',', ".").parse().ok(),
+            }
+        } else if patterns.stopping.is_match(message) {
+            LogEventKind::ServerStopping
+        } else if let Some(captures) = patterns
+            .death
+            .captures(message)
+            // Death messages are too varied to match on their own, only online players can die.
+            .filter(|captures| self.online.contains(&captures["player"]))
+        {
+            LogEventKind::Death {
+                player: player(&captures),
+                message: captures["message"].to_string(),
+            }
+        } else {
+            return None;
+        };
+
+        Some(LogEvent {
+            timestamp,
+            level: record.level,
+            thread: record.thread.map(str::to_string),
+            kind,
+            replayed: false,
+        })
+    }
+}
+
+/// Parses a log file of a server, like `logs/latest.log` or an uncompressed `logs/2026-10-18-1.log`.
+///
+/// The lines only carry the time of day, so the date is taken from the file name, which is the day the
+/// log starts on, or else from the time the file was last modified, which is the day it ends on. The date
+/// moves on by a day each time the time of day wraps around midnight.
+///
+/// # Returns
+/// The events in the file, oldest first.
+pub fn parse_log_file(path: impl AsRef<Path>) -> Result<Vec<LogEvent>, Box<dyn Error>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)?;
+    let lines: Vec<_> = contents.lines().map(strip_ansi).collect();
+    let named_date = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.get(..10))
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+    let mut date = match named_date {
+        Some(date) => date,
+        None => {
+            let modified: chrono::DateTime<Local> = std::fs::metadata(path)?.modified()?.into();
+            let end_date = modified.date_naive();
+            end_date
+                .checked_sub_days(Days::new(midnight_rollovers(&lines)))
+                .unwrap_or(end_date)
+        }
+    };
+
+    let mut parser = LogEventParser::new();
+    let mut previous_time: Option<NaiveTime> = None;
+    let mut events = Vec::new();
+    for line in &lines {
+        let Some(record) = split_line(line) else {
+            events.extend(parser.parse_continuation(line));
+            continue;
+        };
+        match record.date {
+            Some(record_date) => date = record_date,
+            None if rolled_over(previous_time, &record) => date = date.succ_opt().unwrap_or(date),
+            None => {}
+        }
+        previous_time = Some(record.time);
+
+        let timestamp = local_timestamp(date, record.time).unwrap_or_default();
+        events.extend(parser.parse_record(&record, timestamp));
+    }
+    events.extend(parser.flush());
+    Ok(events)
+}
+
+/// Counts how often the lines of a log without dates roll over midnight.
+fn midnight_rollovers(lines: &[std::borrow::Cow<'_, str>]) -> u64 {
+    let mut previous_time = None;
+    let mut rollovers = 0;
+    for record in lines.iter().filter_map(|line| split_line(line)) {
+        if rolled_over(previous_time, &record) {
+            rollovers += 1;
+        }
+        previous_time = Some(record.time);
+    }
+    rollovers
+}
+
+/// Whether a line without a date was logged on the day after the line before it.
+fn rolled_over(previous_time: Option<NaiveTime>, record: &LogRecord) -> bool {
+    record.date.is_none() && previous_time.is_some_and(|previous| record.time < previous)
+}
+
+/// Dates a line of the live console by its own time of day.
+///
+/// The date is the one the line was read on, or the day before if that would put the line more than
+/// 12 hours after it was read, e.g. a line logged at 23:59:59 that is read just after midnight.
+fn live_timestamp(record: &LogRecord, read_at: u64) -> u64 {
+    let Some(read_date) = Local
+        .timestamp_millis_opt(read_at as i64)
+        .single()
+        .map(|read_at| read_at.date_naive())
+    else {
+        return read_at;
+    };
+    let date = match record.date {
+        Some(date) => date,
+        None => match local_timestamp(read_date, record.time) {
+            Some(logged_at) if logged_at > read_at + 12 * 60 * 60 * 1000 => read_date.pred_opt().unwrap_or(read_date),
+            _ => read_date,
+        },
+    };
+    local_timestamp(date, record.time).unwrap_or(read_at)
+}
+
+/// The milliseconds since the Unix epoch of a local date and time.
+fn local_timestamp(date: NaiveDate, time: NaiveTime) -> Option<u64> {
+    Local
+        .from_local_datetime(&NaiveDateTime::new(date, time))
+        .earliest()
+        .map(|time| time.timestamp_millis().max(0) as u64)
+}
+
+/// Removes the color escape sequences from a line.
+fn strip_ansi(line: &str) -> std::borrow::Cow<'_, str> {
+    match PATTERNS.as_ref() {
+        Some(patterns) => patterns.ansi.replace_all(line, ""),
+        None => line.into(),
+    }
+}
+
+/// Splits a line into its prefix and message, `None` if it wasn't written by the server's logger.
+fn split_line(line: &str) -> Option<LogRecord<'_>> {
+    let patterns = PATTERNS.as_ref()?;
+    let captures = patterns
+        .vanilla_prefix
+        .captures(line)
+        .or_else(|| patterns.paper_prefix.captures(line))?;
+    Some(LogRecord {
+        date: captures
+            .name("date")
+            .and_then(|date| NaiveDate::parse_from_str(date.as_str(), "%d%b%Y").ok()),
+        time: NaiveTime::parse_from_str(captures.name("time")?.as_str(), "%H:%M:%S").ok()?,
+        thread: captures.name("thread").map(|thread| thread.as_str()),
+        level: LogLevel::from_str(captures.name("level")?.as_str()).ok()?,
+        message: captures.name("message")?.as_str(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(lines: &[&str]) -> Vec<LogEventKind> {
+        let mut parser = LogEventParser::new();
+        let mut events: Vec<LogEvent> = lines.iter().flat_map(|line| parser.parse(line, 0)).collect();
+        events.extend(parser.flush());
+        events.into_iter().map(|event| event.kind).collect()
+    }
+
+    #[test]
+    fn test_player_session() {
+        let events = kinds(&[
+            "[12:00:00] [User Authenticator #1/INFO]: UUID of player Notch is 069a79f4-44e9-4726-a5be-fca90e38aaf5",
+            "[12:00:00] [Server thread/INFO]: Notch joined the game",
+            "[12:00:05] [Server thread/INFO]: <Notch> hello",
+            "[12:00:06] [Server thread/INFO]: Notch has made the advancement [Stone Age]",
+            "[12:00:07] [Server thread/INFO]: Notch fell from a high place",
+            "[12:00:08] [Server thread/INFO]: Notch lost connection: Disconnected",
+            "[12:00:08] [Server thread/INFO]: Notch left the game",
+        ]);
+        let uuid = Some("069a79f4-44e9-4726-a5be-fca90e38aaf5".to_string());
+        assert_eq!(
+            events,
+            vec![
+                LogEventKind::PlayerJoined {
+                    player: "Notch".into(),
+                    uuid: uuid.clone()
+                },
+                LogEventKind::Chat {
+                    player: "Notch".into(),
+                    message: "hello".into()
+                },
+                LogEventKind::Advancement {
+                    player: "Notch".into(),
+                    advancement: "Stone Age".into()
+                },
+                LogEventKind::Death {
+                    player: "Notch".into(),
+                    message: "fell from a high place".into()
+                },
+                LogEventKind::PlayerLeft {
+                    player: "Notch".into(),
+                    uuid,
+                    reason: Some("Disconnected".into())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_server_formats() {
+        let events = kinds(&[
+            "[12:00:00 INFO]: Done (3.5s)! For help, type \"help\"",
+            "[18Oct2026 12:00:00.123] [Server thread/WARN] [minecraft/MinecraftServer]: Failed: java.lang.IllegalStateException: oops",
+            "[12:00:01 INFO]: Steve issued server command: /gamemode creative",
+            "[12:00:02 INFO]: Steve fell from a high place",
+            "[12:00:03] [Server thread/ERROR]: Encountered an unexpected exception",
+            "net.minecraft.ReportedException: Ticking entity",
+            "\tat net.minecraft.server.MinecraftServer.tick(MinecraftServer.java:1)",
+            "[12:00:04] [Server thread/INFO]: Stopping server",
+        ]);
+        assert_eq!(
+            events,
+            vec![
+                LogEventKind::ServerStarted {
+                    startup_seconds: Some(3.5)
+                },
+                LogEventKind::Problem {
+                    message: "Failed: java.lang.IllegalStateException: oops".into(),
+                    exception: Some("java.lang.IllegalStateException".into())
+                },
+                LogEventKind::CommandIssued {
+                    player: "Steve".into(),
+                    command: "/gamemode creative".into()
+                },
+                // Steve never joined, so the line isn't taken for a death.
+                LogEventKind::Problem {
+                    message: "Encountered an unexpected exception".into(),
+                    exception: Some("net.minecraft.ReportedException".into())
+                },
+                LogEventKind::ServerStopping,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_midnight() -> Result<(), Box<dyn Error>> {
+        let before_midnight = NaiveDate::from_ymd_opt(2026, 10, 17).ok_or("invalid date")?;
+        let after_midnight = NaiveDate::from_ymd_opt(2026, 10, 18).ok_or("invalid date")?;
+        let time = |time: &str| NaiveTime::parse_from_str(time, "%H:%M:%S");
+        let joined = local_timestamp(before_midnight, time("23:59:58")?).ok_or("invalid time")?;
+        let left = local_timestamp(after_midnight, time("00:00:02")?).ok_or("invalid time")?;
+
+        // A line logged just before midnight and read just after it.
+        let mut parser = LogEventParser::new();
+        let events = parser.parse("[23:59:58] [Server thread/INFO]: Notch joined the game", left);
+        assert_eq!(
+            events.iter().map(|event| event.timestamp).collect::<Vec<_>>(),
+            vec![joined]
+        );
+
+        // latest.log is dated by the time it was last written, which is the day its last line was logged on.
+        let directory = std::env::temp_dir().join(format!("log_events_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("latest.log");
+        std::fs::write(
+            &path,
+            "[23:59:58] [Server thread/INFO]: Notch joined the game\n[00:00:02] [Server thread/INFO]: Notch left the game\n",
+        )?;
+        std::fs::File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_millis(left))?;
+        let events = parse_log_file(&path);
+        std::fs::remove_dir_all(&directory)?;
+        assert_eq!(
+            events?.iter().map(|event| event.timestamp).collect::<Vec<_>>(),
+            vec![joined, left]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use crate::server::Server;
+use crate::server_database::ServerDatabase;
+use crate::server_process::is_running;
+use crate::server_properties::ServerProperties;
+use crypto::random::random_alphanumeric;
+use serde_derive::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+
+/// The packet type of a login request.
+const SERVERDATA_AUTH: i32 = 3;
+/// The packet type of the response to a login request.
+const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+/// The packet type of a command.
+const SERVERDATA_EXECCOMMAND: i32 = 2;
+/// The packet type of the response to a command.
+const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+/// The size of a packet without its body: the id, the type and the two terminating null bytes.
+const PACKET_HEADER_SIZE: i32 = 10;
+/// The largest packet accepted, Minecraft splits longer responses into packets of 4096 bytes.
+const MAX_PACKET_SIZE: i32 = 4096 + PACKET_HEADER_SIZE;
+/// The port Minecraft uses for RCON when `rcon.port` isn't set.
+pub const DEFAULT_RCON_PORT: u16 = 25575;
+/// The length of generated RCON passwords.
+const RCON_PASSWORD_LENGTH: usize = 32;
+/// The timeout for connecting to a server and for every read and write.
+const RCON_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A connection to a server over the Source RCON protocol.
+pub struct RconClient {
+    stream: TcpStream,
+    /// The id of the next request, responses carry the id of the request they answer.
+    next_id: i32,
+}
+
+impl RconClient {
+    /// Connects to a server and logs in.
+    ///
+    /// # Arguments
+    /// * `address` - The address of the server's RCON port.
+    /// * `password` - The `rcon.password` of the server.
+    pub fn connect(address: SocketAddr, password: &str) -> Result<Self, Box<dyn Error>> {
+        let stream = TcpStream::connect_timeout(&address, RCON_TIMEOUT)?;
+        stream.set_read_timeout(Some(RCON_TIMEOUT))?;
+        stream.set_write_timeout(Some(RCON_TIMEOUT))?;
+        let mut client = Self { stream, next_id: 1 };
+
+        let id = client.send(SERVERDATA_AUTH, password)?;
+        // Some servers send an empty response value before the login response.
+        loop {
+            let (response_id, kind, _) = client.receive()?;
+            if kind != SERVERDATA_AUTH_RESPONSE {
+                continue;
+            }
+            return match response_id {
+                -1 => Err("RCON login failed, the password is wrong".into()),
+                response_id if response_id == id => Ok(client),
+                _ => Err("Unexpected RCON login response".into()),
+            };
+        }
+    }
+
+    /// Runs a command on the server.
+    ///
+    /// # Returns
+    /// The response of the command, which is empty for commands that don't respond.
+    pub fn command(&mut self, command: &str) -> Result<String, Box<dyn Error>> {
+        let id = self.send(SERVERDATA_EXECCOMMAND, command)?;
+        // Long responses are split over several packets without marking the last one, so an
+        // invalid request is sent after the command, its response marks the end of the command's.
+        let end = self.send(SERVERDATA_RESPONSE_VALUE, "")?;
+
+        let mut response = String::new();
+        loop {
+            let (response_id, _, body) = self.receive()?;
+            if response_id == end {
+                return Ok(response);
+            }
+            if response_id == id {
+                response.push_str(&body);
+            }
+        }
+    }
+
+    /// Sends a packet.
+    ///
+    /// # Returns
+    /// The id of the packet.
+    fn send(&mut self, kind: i32, body: &str) -> Result<i32, Box<dyn Error>> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut packet = Vec::with_capacity(body.len() + 14);
+        packet.extend_from_slice(&(body.len() as i32 + PACKET_HEADER_SIZE).to_le_bytes());
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&kind.to_le_bytes());
+        packet.extend_from_slice(body.as_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        self.stream.write_all(&packet)?;
+        Ok(id)
+    }
+
+    /// Reads a packet.
+    ///
+    /// # Returns
+    /// The id, type and body of the packet.
+    fn receive(&mut self) -> Result<(i32, i32, String), Box<dyn Error>> {
+        let mut size = [0u8; 4];
+        self.stream.read_exact(&mut size)?;
+        let size = i32::from_le_bytes(size);
+        if !(PACKET_HEADER_SIZE..=MAX_PACKET_SIZE).contains(&size) {
+            return Err(format!("Invalid RCON packet size: {}", size).into());
+        }
+
+        let mut packet = vec![0u8; size as usize];
+        self.stream.read_exact(&mut packet)?;
+        let id = i32::from_le_bytes([packet[0], packet[1], packet[2], packet[3]]);
+        let kind = i32::from_le_bytes([packet[4], packet[5], packet[6], packet[7]]);
+        let body = String::from_utf8_lossy(&packet[8..packet.len() - 2]).into_owned();
+        Ok((id, kind, body))
+    }
+}
+
+/// The RCON settings of a server, read from its `server.properties`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RconSettings {
+    /// Whether `enable-rcon` is set to `true`.
+    pub enabled: bool,
+    /// The `rcon.port`.
+    pub port: u16,
+    /// The `rcon.password`, which isn't sent to clients.
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    /// Whether a password is set, Minecraft doesn't open the RCON port without one.
+    pub password_set: bool,
+}
+
+pub trait ServerRcon {
+    /// Reads the RCON settings from `server.properties`.
+    fn rcon_settings(&self) -> Result<RconSettings, Box<dyn Error>>;
+
+    /// Turns RCON on or off, which takes effect the next time the server starts.
+    ///
+    /// Enabling RCON generates a password if none is set, and picks a port that no other server uses
+    /// if the current one is taken.
+    fn set_rcon_enabled(&self, enabled: bool) -> Result<RconSettings, Box<dyn Error>>;
+
+    /// Runs a command on the server over RCON.
+    ///
+    /// # Returns
+    /// The response of the command.
+    fn send_rcon_command(&self, command: impl AsRef<str>) -> Result<String, Box<dyn Error>>;
+}
+
+impl ServerRcon for Server<u64> {
+    fn rcon_settings(&self) -> Result<RconSettings, Box<dyn Error>> {
+        Ok(rcon_settings_from(&self.get_properties()?))
+    }
+
+    fn set_rcon_enabled(&self, enabled: bool) -> Result<RconSettings, Box<dyn Error>> {
+        let settings = self.rcon_settings()?;
+        let mut values = HashMap::from([("enable-rcon".to_string(), enabled.to_string())]);
+        if enabled {
+            if !settings.password_set {
+                values.insert("rcon.password".to_string(), random_alphanumeric(RCON_PASSWORD_LENGTH));
+            }
+            // A running server holds its own RCON port, which would look taken.
+            if !(settings.enabled && is_running(self.id)) {
+                let port = find_rcon_port(self.id, settings.port)?;
+                values.insert("rcon.port".to_string(), port.to_string());
+            }
+        }
+        self.set_property_range(values)?;
+        self.rcon_settings()
+    }
+
+    fn send_rcon_command(&self, command: impl AsRef<str>) -> Result<String, Box<dyn Error>> {
+        let settings = self.rcon_settings()?;
+        if !settings.enabled {
+            return Err("RCON isn't enabled for this server".into());
+        }
+        let password = settings.password.ok_or("RCON password not set")?;
+        let mut client = RconClient::connect(SocketAddr::from(([127, 0, 0, 1], settings.port)), &password)?;
+        client.command(command.as_ref())
+    }
+}
+
+/// Reads the RCON settings from the properties of a server.
+fn rcon_settings_from(properties: &HashMap<String, String>) -> RconSettings {
+    let password = properties
+        .get("rcon.password")
+        .filter(|password| !password.is_empty())
+        .cloned();
+    RconSettings {
+        enabled: properties.get("enable-rcon").is_some_and(|enabled| enabled == "true"),
+        port: properties
+            .get("rcon.port")
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(DEFAULT_RCON_PORT),
+        password_set: password.is_some(),
+        password,
+    }
+}
+
+/// Finds a port for RCON, keeping `preferred` if no other server uses it and it's free.
+fn find_rcon_port(server_id: u64, preferred: u16) -> Result<u16, Box<dyn Error>> {
+    let mut taken: Vec<u16> = Vec::new();
+    for other in Server::get_list_of_servers()? {
+        if other.id == server_id {
+            continue;
+        }
+        let properties = other.get_properties().unwrap_or_default();
+        taken.push(rcon_settings_from(&properties).port);
+        if let Some(port) = properties.get("server-port").and_then(|port| port.parse().ok()) {
+            taken.push(port);
+        }
+    }
+
+    (preferred..=u16::MAX)
+        .chain(DEFAULT_RCON_PORT..preferred)
+        .find(|port| !taken.contains(port) && TcpListener::bind(("0.0.0.0", *port)).is_ok())
+        .ok_or_else(|| "No free port for RCON".into())
+}
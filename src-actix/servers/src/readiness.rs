@@ -0,0 +1,132 @@
+use serde_derive::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// The console line printed by vanilla and the server loaders built on it once the world is loaded.
+pub const DEFAULT_READY_PATTERN: &str = r#"Done \(.*\)! For help, type "help""#;
+
+/// How the panel decides that a server finished starting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReadinessProbe {
+    /// Uses the probe that fits the server's loader.
+    #[default]
+    Loader,
+    /// Waits for a console line matching a regular expression.
+    ConsolePattern,
+    /// Waits until the server's port accepts TCP connections.
+    TcpPort,
+    /// Waits until the server answers a Server List Ping.
+    ServerListPing,
+}
+
+/// Per-server configuration of the readiness detection.
+///
+/// A server that isn't ready within `timeout_seconds` is marked as failed to start.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReadinessCheck {
+    /// How readiness is detected.
+    pub probe: ReadinessProbe,
+    /// The regular expression for `ConsolePattern`, `DEFAULT_READY_PATTERN` if not set.
+    pub pattern: Option<String>,
+    /// The port probed by `TcpPort` and `ServerListPing`, the `server-port` from `server.properties` if not set.
+    pub port: Option<u16>,
+    /// How long the server has to become ready, in seconds.
+    pub timeout_seconds: u64,
+}
+
+impl Default for ReadinessCheck {
+    /// Provides the default check, which uses the loader's probe and allows modded servers time to load.
+    fn default() -> Self {
+        Self {
+            probe: ReadinessProbe::Loader,
+            pattern: None,
+            port: None,
+            timeout_seconds: 600,
+        }
+    }
+}
+
+impl ReadinessCheck {
+    /// Resolves the probe to use for a server.
+    ///
+    /// # Arguments
+    /// * `loader_type` - The loader of the server, as stored in `Server::loader_type`.
+    pub fn resolve_probe(&self, loader_type: u8) -> ReadinessProbe {
+        match self.probe {
+            ReadinessProbe::Loader => match loader_type {
+                // Vanilla, Fabric, Forge, Quilt, NeoForge, Spigot and Paper all print the vanilla line.
+                0..=6 => ReadinessProbe::ConsolePattern,
+                // Anything else, e.g. a proxy set up by hand, is ready once its port is open.
+                _ => ReadinessProbe::TcpPort,
+            },
+            probe => probe,
+        }
+    }
+
+    /// The regular expression the console is matched against.
+    pub fn pattern(&self) -> &str {
+        self.pattern.as_deref().unwrap_or(DEFAULT_READY_PATTERN)
+    }
+}
+
+impl FromStr for ReadinessProbe {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "loader" | "default" => Ok(ReadinessProbe::Loader),
+            "console-pattern" | "console" => Ok(ReadinessProbe::ConsolePattern),
+            "tcp-port" | "tcp" => Ok(ReadinessProbe::TcpPort),
+            "server-list-ping" | "ping" => Ok(ReadinessProbe::ServerListPing),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    #[test]
+    fn test_resolve_probe() {
+        let check = ReadinessCheck::default();
+        for loader_type in 0..=6 {
+            assert_eq!(check.resolve_probe(loader_type), ReadinessProbe::ConsolePattern);
+        }
+        assert_eq!(check.resolve_probe(7), ReadinessProbe::TcpPort);
+
+        // A probe set on the server wins over the loader's.
+        let check = ReadinessCheck {
+            probe: ReadinessProbe::ServerListPing,
+            ..ReadinessCheck::default()
+        };
+        assert_eq!(check.resolve_probe(0), ReadinessProbe::ServerListPing);
+        assert_eq!(check.resolve_probe(7), ReadinessProbe::ServerListPing);
+    }
+
+    #[test]
+    fn test_pattern() -> Result<(), regex::Error> {
+        let check = ReadinessCheck::default();
+        let pattern = Regex::new(check.pattern())?;
+        assert!(pattern.is_match(r#"[12:00:00] [Server thread/INFO]: Done (3.512s)! For help, type "help""#));
+        assert!(!pattern.is_match("[12:00:00] [Server thread/INFO]: Preparing spawn area: 83%"));
+
+        let check = ReadinessCheck {
+            pattern: Some("Listening on".to_string()),
+            ..ReadinessCheck::default()
+        };
+        assert_eq!(check.pattern(), "Listening on");
+        Ok(())
+    }
+
+    #[test]
+    fn test_probe_from_str() {
+        assert_eq!("default".parse(), Ok(ReadinessProbe::Loader));
+        assert_eq!("Console".parse(), Ok(ReadinessProbe::ConsolePattern));
+        assert_eq!("tcp-port".parse(), Ok(ReadinessProbe::TcpPort));
+        assert_eq!("ping".parse(), Ok(ReadinessProbe::ServerListPing));
+        assert_eq!("rcon".parse::<ReadinessProbe>(), Err(()));
+    }
+}
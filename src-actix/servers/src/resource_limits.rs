@@ -0,0 +1,30 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// Per-server limits on the resources the server's process tree can use.
+///
+/// On Linux the limits are enforced through a cgroup v2 created for the server when it starts.
+/// A limit left unset doesn't restrict the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceLimits {
+    /// The share of CPU time the server gets when the host is busy, from 1 to 10000, where 100 is the kernel default.
+    pub cpu_weight: Option<u32>,
+    /// The maximum CPU time in percent of a single core, e.g. 200 for two cores.
+    pub cpu_quota_percent: Option<u32>,
+    /// The maximum memory of the process tree in MB, which has to leave room above the Java heap.
+    pub memory_max_mb: Option<u64>,
+    /// The maximum number of processes and threads.
+    pub pids_max: Option<u64>,
+}
+
+/// The valid range of `ResourceLimits::cpu_weight`.
+pub const CPU_WEIGHT_RANGE: std::ops::RangeInclusive<u32> = 1..=10000;
+/// The largest `ResourceLimits::memory_max_mb` that can still be written to the cgroup in bytes.
+pub const MEMORY_MAX_MB_LIMIT: u64 = u64::MAX / (1024 * 1024);
+
+impl ResourceLimits {
+    /// Checks if none of the limits are set.
+    pub fn is_unlimited(&self) -> bool {
+        self == &Self::default()
+    }
+}
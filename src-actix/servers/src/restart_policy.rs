@@ -0,0 +1,122 @@
+use serde_derive::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Decides when the panel brings a server back up after it exits on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartMode {
+    /// The server is never restarted automatically.
+    Never,
+    /// The server is restarted when it exits with a non-zero exit code.
+    OnCrash,
+    /// The server is restarted whenever it exits without the panel asking it to.
+    Always,
+}
+
+/// Per-server policy for automatically restarting a server that exited unexpectedly.
+///
+/// Restarts are limited to `max_attempts` within a rolling window of `window_seconds`,
+/// and each consecutive attempt waits twice as long as the previous one, starting at
+/// `backoff_seconds` and capped at `max_backoff_seconds`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RestartPolicy {
+    /// When the server should be restarted.
+    pub mode: RestartMode,
+    /// The maximum number of restarts allowed within the window.
+    pub max_attempts: u32,
+    /// The length of the rolling window in seconds.
+    pub window_seconds: u64,
+    /// The delay before the first restart in seconds.
+    pub backoff_seconds: u64,
+    /// The upper bound of the delay between restarts in seconds.
+    pub max_backoff_seconds: u64,
+}
+
+impl Default for RestartPolicy {
+    /// Provides the default policy, which never restarts the server.
+    fn default() -> Self {
+        Self {
+            mode: RestartMode::Never,
+            max_attempts: 3,
+            window_seconds: 600,
+            backoff_seconds: 5,
+            max_backoff_seconds: 300,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Checks if the policy allows restarting a server that exited on its own.
+    ///
+    /// # Arguments
+    /// * `crashed` - Whether the server exited with a non-zero exit code.
+    pub fn should_restart(&self, crashed: bool) -> bool {
+        match self.mode {
+            RestartMode::Never => false,
+            RestartMode::OnCrash => crashed,
+            RestartMode::Always => true,
+        }
+    }
+
+    /// Calculates how long to wait before a restart attempt.
+    ///
+    /// # Arguments
+    /// * `previous_attempts` - The number of restarts already made within the window.
+    pub fn backoff(&self, previous_attempts: u32) -> Duration {
+        let multiplier = 2u64.saturating_pow(previous_attempts);
+        Duration::from_secs(
+            self.backoff_seconds
+                .saturating_mul(multiplier)
+                .min(self.max_backoff_seconds),
+        )
+    }
+}
+
+impl FromStr for RestartMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "never" => Ok(RestartMode::Never),
+            "on-crash" | "on_crash" => Ok(RestartMode::OnCrash),
+            "always" => Ok(RestartMode::Always),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_restart() {
+        let policy = |mode| RestartPolicy {
+            mode,
+            ..RestartPolicy::default()
+        };
+        assert!(!policy(RestartMode::Never).should_restart(true));
+        assert!(policy(RestartMode::OnCrash).should_restart(true));
+        assert!(!policy(RestartMode::OnCrash).should_restart(false));
+        assert!(policy(RestartMode::Always).should_restart(false));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RestartPolicy::default();
+        let backoffs: Vec<u64> = (0..8).map(|attempts| policy.backoff(attempts).as_secs()).collect();
+        assert_eq!(backoffs, vec![5, 10, 20, 40, 80, 160, 300, 300]);
+        // The multiplier saturates instead of overflowing.
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_restart_mode_from_str() {
+        assert_eq!("On-Crash".parse(), Ok(RestartMode::OnCrash));
+        assert_eq!("on_crash".parse(), Ok(RestartMode::OnCrash));
+        assert_eq!("always".parse(), Ok(RestartMode::Always));
+        assert_eq!("sometimes".parse::<RestartMode>(), Err(()));
+    }
+}
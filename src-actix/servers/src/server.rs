@@ -1,3 +1,8 @@
+use crate::idle_policy::IdlePolicy;
+use crate::jvm_options::JvmOptions;
+use crate::readiness::ReadinessCheck;
+use crate::resource_limits::ResourceLimits;
+use crate::restart_policy::RestartPolicy;
 use crate::server_database::ServerDatabase;
 use crate::server_filesystem::ServerFilesystem;
 use crate::server_status::ServerStatus;
@@ -5,6 +10,7 @@ use crypto::hashids::{decode, encode};
 use serde::de::{self, Deserializer, MapAccess, Visitor};
 use serde::{Deserialize, Serialize, Serializer};
 use std::any::Any;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt;
 use std::path::PathBuf;
@@ -130,6 +136,14 @@ impl ToHashed for Server<u64> {
             updated_at: self.updated_at.clone(),
             status: self.status.clone(),
             pid: self.pid,
+            restart_policy: self.restart_policy.clone(),
+            auto_start_order: self.auto_start_order,
+            resource_limits: self.resource_limits.clone(),
+            readiness: self.readiness.clone(),
+            jvm_options: self.jvm_options.clone(),
+            idle_policy: self.idle_policy.clone(),
+            environment: self.environment.clone(),
+            start_command: self.start_command.clone(),
         })
     }
 }
@@ -176,6 +190,22 @@ pub struct Server<T: Identifier> {
     pub status: Option<ServerStatus>,
     /// If the server is running, this will be the process id, otherwise it will be none.
     pub pid: Option<u64>,
+    /// The policy for automatically restarting the server after it exits unexpectedly.
+    pub restart_policy: RestartPolicy,
+    /// The order in which auto-start servers are started when the panel boots, lower values start first.
+    pub auto_start_order: u32,
+    /// The limits on the CPU, memory and processes the server can use, enforced with cgroups on Linux.
+    pub resource_limits: ResourceLimits,
+    /// How the panel detects that the server finished starting.
+    pub readiness: ReadinessCheck,
+    /// The structured JVM options: the preset and the garbage collector.
+    pub jvm_options: JvmOptions,
+    /// Settings for stopping the server while no players are online and starting it when one tries to join.
+    pub idle_policy: IdlePolicy,
+    /// Environment variables set for the server process, on top of the panel's own.
+    pub environment: BTreeMap<String, String>,
+    /// Template for the command that starts the server, replacing the command built from the start script.
+    pub start_command: Option<String>,
 }
 
 // Default implementation for `Server<u64>`.
@@ -228,6 +258,14 @@ impl Default for Server<u64> {
             size: 0,
             java_runtime: None,
             pid: None,
+            restart_policy: RestartPolicy::default(),
+            auto_start_order: 0,
+            resource_limits: ResourceLimits::default(),
+            readiness: ReadinessCheck::default(),
+            jvm_options: JvmOptions::default(),
+            idle_policy: IdlePolicy::default(),
+            environment: BTreeMap::new(),
+            start_command: None,
         }
     }
 }
@@ -284,6 +322,14 @@ impl Default for Server<String> {
             size: 0,
             java_runtime: None,
             pid: None,
+            restart_policy: RestartPolicy::default(),
+            auto_start_order: 0,
+            resource_limits: ResourceLimits::default(),
+            readiness: ReadinessCheck::default(),
+            jvm_options: JvmOptions::default(),
+            idle_policy: IdlePolicy::default(),
+            environment: BTreeMap::new(),
+            start_command: None,
         }
     }
 }
@@ -374,6 +420,22 @@ impl Serialize for Server<u64> {
 
         state.serialize_field("minecraft_version", &self.minecraft_version)?;
 
+        state.serialize_field("restart_policy", &self.restart_policy)?;
+
+        state.serialize_field("auto_start_order", &self.auto_start_order)?;
+
+        state.serialize_field("resource_limits", &self.resource_limits)?;
+
+        state.serialize_field("readiness", &self.readiness)?;
+
+        state.serialize_field("jvm_options", &self.jvm_options)?;
+
+        state.serialize_field("idle_policy", &self.idle_policy)?;
+
+        state.serialize_field("environment", &self.environment)?;
+
+        state.serialize_field("start_command", &self.start_command)?;
+
         // Ends the serialization process for the `Server` struct
         state.end()
     }
@@ -405,6 +467,14 @@ impl<'de> Deserialize<'de> for Server<u64> {
             JavaRuntime,
             Size,
             MinecraftVersion,
+            RestartPolicy,
+            AutoStartOrder,
+            ResourceLimits,
+            Readiness,
+            JvmOptions,
+            IdlePolicy,
+            Environment,
+            StartCommand,
         }
 
         struct ServerVisitor;
@@ -440,6 +510,14 @@ impl<'de> Deserialize<'de> for Server<u64> {
                 let mut java_runtime = None;
                 let mut size = None;
                 let mut minecraft_version = None;
+                let mut restart_policy = None;
+                let mut auto_start_order = None;
+                let mut resource_limits = None;
+                let mut readiness = None;
+                let mut jvm_options = None;
+                let mut idle_policy = None;
+                let mut environment = None;
+                let mut start_command = None;
 
                 // Iterate over each key-value pair in the map
                 while let Some(key) = map.next_key()? {
@@ -562,6 +640,54 @@ impl<'de> Deserialize<'de> for Server<u64> {
                             }
                             minecraft_version = Some(map.next_value()?);
                         }
+                        Field::RestartPolicy => {
+                            if restart_policy.is_some() {
+                                return Err(de::Error::duplicate_field("restart_policy"));
+                            }
+                            restart_policy = Some(map.next_value()?);
+                        }
+                        Field::AutoStartOrder => {
+                            if auto_start_order.is_some() {
+                                return Err(de::Error::duplicate_field("auto_start_order"));
+                            }
+                            auto_start_order = Some(map.next_value()?);
+                        }
+                        Field::ResourceLimits => {
+                            if resource_limits.is_some() {
+                                return Err(de::Error::duplicate_field("resource_limits"));
+                            }
+                            resource_limits = Some(map.next_value()?);
+                        }
+                        Field::Readiness => {
+                            if readiness.is_some() {
+                                return Err(de::Error::duplicate_field("readiness"));
+                            }
+                            readiness = Some(map.next_value()?);
+                        }
+                        Field::JvmOptions => {
+                            if jvm_options.is_some() {
+                                return Err(de::Error::duplicate_field("jvm_options"));
+                            }
+                            jvm_options = Some(map.next_value()?);
+                        }
+                        Field::IdlePolicy => {
+                            if idle_policy.is_some() {
+                                return Err(de::Error::duplicate_field("idle_policy"));
+                            }
+                            idle_policy = Some(map.next_value()?);
+                        }
+                        Field::Environment => {
+                            if environment.is_some() {
+                                return Err(de::Error::duplicate_field("environment"));
+                            }
+                            environment = Some(map.next_value()?);
+                        }
+                        Field::StartCommand => {
+                            if start_command.is_some() {
+                                return Err(de::Error::duplicate_field("start_command"));
+                            }
+                            start_command = Some(map.next_value()?);
+                        }
                     }
                 }
 
@@ -586,6 +712,14 @@ impl<'de> Deserialize<'de> for Server<u64> {
                 let size = size.ok_or_else(|| de::Error::missing_field("size"))?;
                 let minecraft_version =
                     minecraft_version.ok_or_else(|| de::Error::missing_field("minecraft_version"))?;
+                let restart_policy = restart_policy.unwrap_or_default();
+                let auto_start_order = auto_start_order.unwrap_or_default();
+                let resource_limits = resource_limits.unwrap_or_default();
+                let readiness = readiness.unwrap_or_default();
+                let jvm_options = jvm_options.unwrap_or_default();
+                let idle_policy = idle_policy.unwrap_or_default();
+                let environment = environment.unwrap_or_default();
+                let start_command = start_command.unwrap_or_default();
 
                 // Construct and return the Server object
                 Ok(Server {
@@ -609,6 +743,14 @@ impl<'de> Deserialize<'de> for Server<u64> {
                     size,
                     minecraft_version,
                     pid: None,
+                    restart_policy,
+                    auto_start_order,
+                    resource_limits,
+                    readiness,
+                    jvm_options,
+                    idle_policy,
+                    environment,
+                    start_command,
                 })
             }
         }
@@ -633,6 +775,14 @@ impl<'de> Deserialize<'de> for Server<u64> {
             "java_runtime",
             "size",
             "minecraft_version",
+            "restart_policy",
+            "auto_start_order",
+            "resource_limits",
+            "readiness",
+            "jvm_options",
+            "idle_policy",
+            "environment",
+            "start_command",
         ];
         deserializer.deserialize_struct("Server", FIELDS, ServerVisitor)
     }
@@ -661,6 +811,14 @@ impl Clone for Server<u64> {
             status: self.status.clone(),
             size: self.size,
             pid: self.pid,
+            restart_policy: self.restart_policy.clone(),
+            auto_start_order: self.auto_start_order,
+            resource_limits: self.resource_limits.clone(),
+            readiness: self.readiness.clone(),
+            jvm_options: self.jvm_options.clone(),
+            idle_policy: self.idle_policy.clone(),
+            environment: self.environment.clone(),
+            start_command: self.start_command.clone(),
         }
     }
 }
@@ -687,6 +845,14 @@ impl Clone for Server<String> {
             status: self.status.clone(),
             size: self.size,
             pid: self.pid,
+            restart_policy: self.restart_policy.clone(),
+            auto_start_order: self.auto_start_order,
+            resource_limits: self.resource_limits.clone(),
+            readiness: self.readiness.clone(),
+            jvm_options: self.jvm_options.clone(),
+            idle_policy: self.idle_policy.clone(),
+            environment: self.environment.clone(),
+            start_command: self.start_command.clone(),
         }
     }
 }
@@ -744,5 +910,13 @@ impl PartialEq for Server<u64> {
             && self.java_runtime == other.java_runtime
             && self.size == other.size
             && self.minecraft_version == other.minecraft_version
+            && self.restart_policy == other.restart_policy
+            && self.auto_start_order == other.auto_start_order
+            && self.resource_limits == other.resource_limits
+            && self.readiness == other.readiness
+            && self.jvm_options == other.jvm_options
+            && self.idle_policy == other.idle_policy
+            && self.environment == other.environment
+            && self.start_command == other.start_command
     }
 }
@@ -0,0 +1,90 @@
+use database::{create_appdb_connection, last_inserted_id};
+use serde_derive::Serialize;
+use sqlite::State;
+use std::error::Error;
+
+/// The number of console lines kept with each crash record.
+pub const CRASH_CONSOLE_LINES: usize = 100;
+
+/// A record of a server exiting unexpectedly.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerCrash {
+    /// The unique identifier of the crash record.
+    pub id: u64,
+    /// The exit code of the process, `None` if it was killed by a signal or the code is unknown.
+    pub exit_code: Option<i32>,
+    /// The time of the crash, formatted as `YYYY-MM-DD HH:MM:SS` in UTC.
+    pub crashed_at: String,
+    /// The last console lines printed before the crash, oldest first.
+    pub console: Vec<String>,
+}
+
+/// Creates the `server_crash` table if it doesn't already exist.
+///
+/// # Arguments
+///
+/// * `conn` - The connection to the application database.
+pub(crate) fn initialize_crash_database(conn: &sqlite::Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `server_crash` (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,                       -- Unique identifier for each crash
+            server INTEGER NOT NULL,                                    -- ID of the server that crashed
+            exit_code INTEGER NULL DEFAULT NULL,                        -- Exit code of the process, nullable if killed by a signal
+            crashed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,    -- Timestamp of the crash
+            console TEXT NOT NULL                                       -- Last console lines before the crash, separated by newlines
+        );
+"#,
+    )?;
+    Ok(())
+}
+
+/// Records a crash in the server's crash history.
+///
+/// # Arguments
+///
+/// * `server_id` - The ID of the server that crashed.
+/// * `exit_code` - The exit code of the process, if any.
+/// * `console` - The last console lines printed before the crash.
+///
+/// # Returns
+///
+/// * `Result<u64, Box<dyn Error>>` - The ID of the new crash record.
+pub fn record_crash(server_id: u64, exit_code: Option<i32>, console: &[String]) -> Result<u64, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare("INSERT INTO server_crash (server, exit_code, console) VALUES (?, ?, ?)")?;
+    statement.bind((1, server_id as i64))?;
+    statement.bind((2, exit_code.map(|code| code as i64)))?;
+    statement.bind((3, console.join("\n").as_str()))?;
+    statement.next()?;
+
+    last_inserted_id("server_crash")
+}
+
+/// Retrieves the most recent crashes of a server, newest first.
+///
+/// # Arguments
+///
+/// * `server_id` - The ID of the server.
+/// * `limit` - The maximum number of crashes to return.
+pub fn get_crashes(server_id: u64, limit: u64) -> Result<Vec<ServerCrash>, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare("SELECT * FROM server_crash WHERE server = ? ORDER BY id DESC LIMIT ?")?;
+    statement.bind((1, server_id as i64))?;
+    statement.bind((2, limit as i64))?;
+
+    let mut crashes = Vec::new();
+    while let State::Row = statement.next()? {
+        crashes.push(ServerCrash {
+            id: statement.read::<i64, _>("id")? as u64,
+            exit_code: statement.read::<Option<i64>, _>("exit_code")?.map(|code| code as i32),
+            crashed_at: statement.read::<String, _>("crashed_at")?,
+            console: statement
+                .read::<String, _>("console")?
+                .lines()
+                .map(str::to_string)
+                .collect(),
+        });
+    }
+    Ok(crashes)
+}
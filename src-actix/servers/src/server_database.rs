@@ -1,5 +1,10 @@
 use crate::server::Server;
+use crate::server_crash::initialize_crash_database;
+use crate::server_eula::initialize_eula_database;
+use crate::server_players::{initialize_player_database, untrack_players};
+use crate::server_schedules::{delete_server_schedules, initialize_schedule_database};
 use crate::server_status::ServerStatus;
+use crate::wake_listener::stop_wake_listener;
 use database::{create_appdb_connection, last_inserted_id};
 use log::info;
 use sqlite::State;
@@ -7,6 +12,9 @@ use std::error::Error;
 use std::path::{Path, PathBuf};
 use crypto::hashids::decode;
 
+/// The `user_version` of the database from which the heap of the servers is stored in MB instead of GB.
+const RAM_IN_MB_VERSION: i64 = 1;
+
 /// Initializes the server database by creating necessary tables.
 ///
 /// This function ensures that the `server` table is created in the database
@@ -42,11 +50,25 @@ pub fn initialize_server_database() -> Result<(), Box<dyn Error>> {
             auto_start BOOLEAN NOT NULL DEFAULT 0,                      -- Whether the server should automatically start on server startup, cannot be NULL
             created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,    -- Timestamp of creation, stored in ISO 8601 format, cannot be NULL
             updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,    -- Timestamp of last update, stored in ISO 8601 format, cannot be NULL
-            status TEXT                                                 -- Current status of the server, stored as a string (e.g., "active", "inactive"), nullable
+            status TEXT,                                                -- Current status of the server, stored as a string (e.g., "active", "inactive"), nullable
+            restart_policy TEXT NULL DEFAULT NULL,                      -- Automatic restart policy serialized as JSON, nullable
+            auto_start_order INTEGER NOT NULL DEFAULT 0,                -- Order in which auto-start servers are started, lower values start first
+            pid INTEGER NULL DEFAULT NULL,                              -- Process ID of the running server, nullable if the server isn't running
+            resource_limits TEXT NULL DEFAULT NULL,                     -- CPU, memory and process limits serialized as JSON, nullable
+            readiness TEXT NULL DEFAULT NULL,                           -- Readiness detection settings serialized as JSON, nullable
+            jvm_options TEXT NULL DEFAULT NULL,                         -- JVM preset and garbage collector serialized as JSON, nullable
+            idle_policy TEXT NULL DEFAULT NULL,                         -- Idle shutdown and wake settings serialized as JSON, nullable
+            environment TEXT NULL DEFAULT NULL,                         -- Environment variables of the server process serialized as JSON, nullable
+            start_command TEXT NULL DEFAULT NULL                        -- Template for the start command, nullable to build it from the start script
         );
 "#;
     let conn = create_appdb_connection()?; // Establish a connection to the application database
     conn.execute(query)?; // Execute the SQL query to create the table
+    migrate_server_table(&conn)?; // Add any columns missing from databases created by older versions
+    initialize_crash_database(&conn)?; // Create the crash history table
+    initialize_eula_database(&conn)?; // Create the EULA acceptance table
+    initialize_player_database(&conn)?; // Create the player session history table
+    initialize_schedule_database(&conn)?; // Create the scheduled commands table
 
     // Check if the 'servers' directory exists, if not, create it
     if !Path::exists("servers".as_ref()) {
@@ -56,6 +78,108 @@ pub fn initialize_server_database() -> Result<(), Box<dyn Error>> {
     Ok(()) // Return success
 }
 
+/// Adds the columns introduced after the first release to an existing `server` table.
+///
+/// `CREATE TABLE IF NOT EXISTS` leaves tables created by older versions untouched,
+/// so every column added later has to be listed here as well.
+///
+/// # Arguments
+///
+/// * `conn` - The connection to the application database.
+fn migrate_server_table(conn: &sqlite::Connection) -> Result<(), Box<dyn Error>> {
+    // Databases from before the JVM options stored the heap in GB, which has to be known before the columns are added.
+    let heap_in_gb = !table_columns(conn, "server")?
+        .iter()
+        .any(|column| column == "jvm_options");
+
+    // Columns added after the initial schema, along with their definitions
+    let columns = [
+        ("restart_policy", "TEXT NULL DEFAULT NULL"),
+        ("auto_start_order", "INTEGER NOT NULL DEFAULT 0"),
+        ("pid", "INTEGER NULL DEFAULT NULL"),
+        ("resource_limits", "TEXT NULL DEFAULT NULL"),
+        ("readiness", "TEXT NULL DEFAULT NULL"),
+        ("jvm_options", "TEXT NULL DEFAULT NULL"),
+        ("idle_policy", "TEXT NULL DEFAULT NULL"),
+        ("environment", "TEXT NULL DEFAULT NULL"),
+        ("start_command", "TEXT NULL DEFAULT NULL"),
+    ];
+
+    add_missing_columns(conn, "server", &columns)?;
+    migrate_schema_version(conn, heap_in_gb)
+}
+
+/// Runs the data migrations the database hasn't had yet, tracked by the `user_version` of the database.
+///
+/// Each migration runs in a transaction along with the bump of the version, so it's applied exactly once.
+///
+/// # Arguments
+///
+/// * `conn` - The connection to the application database.
+/// * `heap_in_gb` - Whether the database predates the JVM options, when the heap was stored in GB.
+fn migrate_schema_version(conn: &sqlite::Connection, heap_in_gb: bool) -> Result<(), Box<dyn Error>> {
+    let mut statement = conn.prepare("PRAGMA user_version")?;
+    statement.next()?;
+    let version = statement.read::<i64, _>(0)?;
+    drop(statement);
+
+    if version < RAM_IN_MB_VERSION {
+        // The heap was passed to Java in GB before the JVM options were added, it's in MB since.
+        let convert = match heap_in_gb {
+            true => "UPDATE server SET min_ram = min_ram * 1024, max_ram = max_ram * 1024;",
+            false => "",
+        };
+        conn.execute(format!(
+            "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+            convert, RAM_IN_MB_VERSION
+        ))?;
+        if heap_in_gb {
+            info!("Converted the memory of existing servers from GB to MB");
+        }
+    }
+    Ok(())
+}
+
+/// Lists the names of the columns of a table.
+fn table_columns(conn: &sqlite::Connection, table: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut columns = Vec::new();
+    let mut statement = conn.prepare(format!("PRAGMA table_info({})", table))?;
+    while let State::Row = statement.next()? {
+        columns.push(statement.read::<String, _>("name")?);
+    }
+    Ok(columns)
+}
+
+/// Adds the columns a table is missing, for tables that got columns after their initial schema.
+///
+/// # Arguments
+///
+/// * `conn` - The connection to the application database.
+/// * `table` - The name of the table.
+/// * `columns` - The names of the columns, along with their definitions.
+///
+/// # Returns
+///
+/// The names of the columns that were added.
+pub(crate) fn add_missing_columns<'a>(
+    conn: &sqlite::Connection,
+    table: &str,
+    columns: &[(&'a str, &str)],
+) -> Result<Vec<&'a str>, Box<dyn Error>> {
+    // Collect the names of the columns that already exist
+    let existing_columns = table_columns(conn, table)?;
+
+    let mut added = Vec::new();
+    for (name, definition) in columns {
+        if !existing_columns.iter().any(|column| column == name) {
+            conn.execute(format!("ALTER TABLE {} ADD COLUMN {} {}", table, name, definition))?;
+            info!("Added column {} to the {} table", name, table);
+            added.push(*name);
+        }
+    }
+    Ok(added)
+}
+
 /// A trait for managing server databases, including adding, updating,
 /// deleting, and retrieving server information.
 pub trait ServerDatabase {
@@ -72,6 +196,9 @@ pub trait ServerDatabase {
 
     /// Updates the current server's details.
     ///
+    /// The status and the process ID are left as they are, they follow the server's process and are
+    /// only written by `update_server_status` and `update_server_pid`.
+    ///
     /// # Returns
     ///
     /// * `Result<(), Box<dyn Error>>` - An empty result indicating success or an error if the update fails.
@@ -171,8 +298,10 @@ impl ServerDatabase for Server<u64> {
         let query = r#"
   INSERT INTO server
   (name, owner, members, min_ram, max_ram, start_script, minecraft_arguments, 
-  java_arguments, loader_type, loader_version, directory, status, java_runtime, size, minecraft_version)
-  VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+  java_arguments, loader_type, loader_version, directory, status, java_runtime, size, minecraft_version,
+  restart_policy, auto_start_order, pid, resource_limits, readiness, jvm_options, idle_policy,
+  environment, start_command)
+  VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
 "#;
 
         // Prepare the SQL insert statement
@@ -215,6 +344,15 @@ impl ServerDatabase for Server<u64> {
         statement.bind((13, self.java_runtime.as_ref().unwrap_or(&PathBuf::from("")).to_str()))?; // Bind the java runtime path.
         statement.bind((14, self.size as i64))?; // Bind the server size
         statement.bind((15, self.minecraft_version.as_str()))?; // Bind Minecraft version
+        statement.bind((16, serde_json::to_string(&self.restart_policy)?.as_str()))?; // Bind the restart policy as JSON
+        statement.bind((17, self.auto_start_order as i64))?; // Bind the auto-start order
+        statement.bind((18, self.pid.map(|pid| pid as i64)))?; // Bind the process ID
+        statement.bind((19, serde_json::to_string(&self.resource_limits)?.as_str()))?; // Bind the resource limits as JSON
+        statement.bind((20, serde_json::to_string(&self.readiness)?.as_str()))?; // Bind the readiness detection settings as JSON
+        statement.bind((21, serde_json::to_string(&self.jvm_options)?.as_str()))?; // Bind the JVM options as JSON
+        statement.bind((22, serde_json::to_string(&self.idle_policy)?.as_str()))?; // Bind the idle policy as JSON
+        statement.bind((23, serde_json::to_string(&self.environment)?.as_str()))?; // Bind the environment variables as JSON
+        statement.bind((24, self.start_command.as_deref()))?; // Bind the start command template
 
         // Execute the SQL statement
         statement.next()?;
@@ -253,12 +391,19 @@ java_arguments = ?,
 loader_type = ?,
 loader_version = ?,
 directory = ?,
-status = ?,
 java_runtime = ?,
 size = ?,
 minecraft_version = ?,
 updated_at = CURRENT_TIMESTAMP,
-auto_start = ?
+auto_start = ?,
+restart_policy = ?,
+auto_start_order = ?,
+resource_limits = ?,
+readiness = ?,
+jvm_options = ?,
+idle_policy = ?,
+environment = ?,
+start_command = ?
 WHERE id = ?
 "#;
 
@@ -312,30 +457,44 @@ WHERE id = ?
         // Bind the server directory path to the eleventh placeholder (index 11)
         statement.bind((11, self.directory.to_str().unwrap_or("")))?;
 
-        // Bind the server status to the twelfth placeholder (index 12)
-        statement.bind((
-            12,
-            self.status
-                .as_ref()
-                .unwrap_or(&ServerStatus::Offline)
-                .to_string()
-                .as_str(),
-        ))?;
+        // Bind the Java runtime path to the twelfth placeholder (index 12)
+        statement.bind((12, self.java_runtime.as_ref().unwrap_or(&PathBuf::from("")).to_str()))?;
+
+        // Bind the server size to the thirteenth placeholder (index 13)
+        statement.bind((13, self.size as i64))?;
+
+        // Bind the Minecraft version to the fourteenth placeholder (index 14)
+        statement.bind((14, self.minecraft_version.as_str()))?;
+
+        // Bind the auto_start field to the fifteenth placeholder (index 15)
+        statement.bind((15, self.auto_start as i64))?;
+
+        // Bind the restart policy as JSON to the sixteenth placeholder (index 16)
+        statement.bind((16, serde_json::to_string(&self.restart_policy)?.as_str()))?;
+
+        // Bind the auto-start order to the seventeenth placeholder (index 17)
+        statement.bind((17, self.auto_start_order as i64))?;
+
+        // Bind the resource limits as JSON to the eighteenth placeholder (index 18)
+        statement.bind((18, serde_json::to_string(&self.resource_limits)?.as_str()))?;
+
+        // Bind the readiness detection settings as JSON to the nineteenth placeholder (index 19)
+        statement.bind((19, serde_json::to_string(&self.readiness)?.as_str()))?;
 
-        // Bind the Java runtime path to the thirteenth placeholder (index 13)
-        statement.bind((13, self.java_runtime.as_ref().unwrap_or(&PathBuf::from("")).to_str()))?;
+        // Bind the JVM options as JSON to the twentieth placeholder (index 20)
+        statement.bind((20, serde_json::to_string(&self.jvm_options)?.as_str()))?;
 
-        // Bind the server size to the fourteenth placeholder (index 14)
-        statement.bind((14, self.size as i64))?;
+        // Bind the idle policy as JSON to the twenty-first placeholder (index 21)
+        statement.bind((21, serde_json::to_string(&self.idle_policy)?.as_str()))?;
 
-        // Bind the Minecraft version to the fifteenth placeholder (index 15)
-        statement.bind((15, self.minecraft_version.as_str()))?;
+        // Bind the environment variables as JSON to the twenty-second placeholder (index 22)
+        statement.bind((22, serde_json::to_string(&self.environment)?.as_str()))?;
 
-        // Bind the auto_start field to the sixteenth placeholder (index 16)
-        statement.bind((16, self.auto_start as i64))?;
+        // Bind the start command template to the twenty-third placeholder (index 23)
+        statement.bind((23, self.start_command.as_deref()))?;
 
-        // Bind the server ID to the seventeenth placeholder (index 17) to specify which record to update
-        statement.bind((17, self.id as i64))?;
+        // Bind the server ID to the twenty-fourth placeholder (index 24) to specify which record to update
+        statement.bind((24, self.id as i64))?;
 
         // Execute the next statement in the prepared sequence
         statement.next()?;
@@ -364,6 +523,30 @@ WHERE id = ?
 
         // Execute the statement
         statement.next()?;
+
+        // Remove the crash history that belongs to the server
+        let mut statement = conn.prepare(r#"DELETE FROM server_crash WHERE server = ?"#)?;
+        statement.bind((1, self.id as i64))?;
+        statement.next()?;
+
+        // Remove the record of who accepted the EULA
+        let mut statement = conn.prepare(r#"DELETE FROM server_eula WHERE server = ?"#)?;
+        statement.bind((1, self.id as i64))?;
+        statement.next()?;
+
+        // Remove the player session history
+        let mut statement = conn.prepare(r#"DELETE FROM player_session WHERE server = ?"#)?;
+        statement.bind((1, self.id as i64))?;
+        statement.next()?;
+
+        // Remove the scheduled commands and stop running them
+        delete_server_schedules(&conn, self.id)?;
+
+        // Release the port of a sleeping server
+        stop_wake_listener(self.id)?;
+
+        // Stop following the players of the server
+        untrack_players(self.id);
         Ok(())
     }
 
@@ -552,7 +735,59 @@ fn get_server_from_statement(statement: &mut sqlite::Statement) -> Result<Server
         // Minecraft Version: Read the "minecraft_version" column as a String.
         minecraft_version: statement.read::<String, _>("minecraft_version")?,
 
-        // I/O streams: Initialize as None, as they are not detailed in the statement.
-        pid: None,
+        // Process ID: Read the "pid" column of the running server, if any.
+        pid: statement.read::<Option<i64>, _>("pid")?.map(|pid| pid as u64),
+
+        // Restart Policy: Parse the JSON column, falling back to the default policy if it's missing or invalid.
+        restart_policy: statement
+            .read::<String, _>("restart_policy")
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+
+        // Auto Start Order: Read the "auto_start_order" column and convert it to u32.
+        auto_start_order: statement.read::<i64, _>("auto_start_order")? as u32,
+
+        // Resource Limits: Parse the JSON column, falling back to no limits if it's missing or invalid.
+        resource_limits: statement
+            .read::<String, _>("resource_limits")
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+
+        // Readiness: Parse the JSON column, falling back to the loader's probe if it's missing or invalid.
+        readiness: statement
+            .read::<String, _>("readiness")
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+
+        // JVM Options: Parse the JSON column, falling back to no preset if it's missing or invalid.
+        jvm_options: statement
+            .read::<String, _>("jvm_options")
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+
+        // Idle Policy: Parse the JSON column, falling back to never stopping the server if it's missing or invalid.
+        idle_policy: statement
+            .read::<String, _>("idle_policy")
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+
+        // Environment: Parse the JSON column, falling back to no extra variables if it's missing or invalid.
+        environment: statement
+            .read::<String, _>("environment")
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+
+        // Start Command: Read the template, NULL or empty builds the command from the start script.
+        start_command: statement
+            .read::<Option<String>, _>("start_command")
+            .ok()
+            .flatten()
+            .filter(|s| !s.is_empty()),
     })
 }
@@ -0,0 +1,100 @@
+use crate::server::Server;
+use database::create_appdb_connection;
+use serde_derive::Serialize;
+use sqlite::State;
+use std::error::Error;
+use std::path::Path;
+
+/// The URL of the Minecraft EULA users have to accept before a server can start.
+pub const EULA_URL: &str = "https://aka.ms/MinecraftEULA";
+
+/// Whether the Minecraft EULA has been accepted for a server, and by whom.
+#[derive(Debug, Clone, Serialize)]
+pub struct EulaStatus {
+    /// Whether `eula.txt` in the server directory contains `eula=true`.
+    pub accepted: bool,
+    /// The ID of the user who last accepted the EULA through the panel, if anyone did.
+    pub accepted_by: Option<u64>,
+    /// The time the EULA was last accepted through the panel, formatted as `YYYY-MM-DD HH:MM:SS` in UTC.
+    pub accepted_at: Option<String>,
+    /// The URL of the EULA, to show when asking the user to accept it.
+    pub url: &'static str,
+}
+
+/// Creates the `server_eula` table if it doesn't already exist.
+///
+/// # Arguments
+///
+/// * `conn` - The connection to the application database.
+pub(crate) fn initialize_eula_database(conn: &sqlite::Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `server_eula` (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,                       -- Unique identifier for each acceptance
+            server INTEGER NOT NULL,                                    -- ID of the server the EULA was accepted for
+            user INTEGER NOT NULL,                                      -- ID of the user who accepted the EULA
+            accepted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP    -- Timestamp of the acceptance
+        );
+"#,
+    )?;
+    Ok(())
+}
+
+/// Checks if `eula.txt` in a directory accepts the EULA.
+pub(crate) fn is_eula_accepted(directory: &Path) -> bool {
+    std::fs::read_to_string(directory.join("eula.txt"))
+        .map(|eula| eula.lines().any(|line| line.trim().eq_ignore_ascii_case("eula=true")))
+        .unwrap_or(false)
+}
+
+pub trait ServerEula {
+    /// Accepts the Minecraft EULA for the server on behalf of a user.
+    ///
+    /// Writes `eula=true` to `eula.txt` in the server directory and records who accepted it.
+    ///
+    /// # Arguments
+    /// * `user_id` - The ID of the user accepting the EULA.
+    fn accept_eula(&self, user_id: u64) -> Result<(), Box<dyn Error>>;
+
+    /// Reads whether the EULA has been accepted, and the latest acceptance recorded by the panel.
+    fn get_eula_status(&self) -> Result<EulaStatus, Box<dyn Error>>;
+}
+
+impl ServerEula for Server<u64> {
+    fn accept_eula(&self, user_id: u64) -> Result<(), Box<dyn Error>> {
+        std::fs::write(
+            self.directory.join("eula.txt"),
+            format!(
+                "# By changing the setting below to TRUE you are indicating your agreement to our EULA ({}).\n\
+                 # Accepted through the panel by user {}\n\
+                 eula=true\n",
+                EULA_URL, user_id
+            ),
+        )?;
+
+        let conn = create_appdb_connection()?;
+        let mut statement = conn.prepare("INSERT INTO server_eula (server, user) VALUES (?, ?)")?;
+        statement.bind((1, self.id as i64))?;
+        statement.bind((2, user_id as i64))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    fn get_eula_status(&self) -> Result<EulaStatus, Box<dyn Error>> {
+        let conn = create_appdb_connection()?;
+        let mut statement = conn.prepare("SELECT * FROM server_eula WHERE server = ? ORDER BY id DESC LIMIT 1")?;
+        statement.bind((1, self.id as i64))?;
+
+        let mut status = EulaStatus {
+            accepted: is_eula_accepted(&self.directory),
+            accepted_by: None,
+            accepted_at: None,
+            url: EULA_URL,
+        };
+        if let State::Row = statement.next()? {
+            status.accepted_by = Some(statement.read::<i64, _>("user")? as u64);
+            status.accepted_at = Some(statement.read::<String, _>("accepted_at")?);
+        }
+        Ok(status)
+    }
+}
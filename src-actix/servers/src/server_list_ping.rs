@@ -0,0 +1,359 @@
+use crate::server::Server;
+use crate::start_validation::server_port;
+use serde_derive::Serialize;
+use serde_json::Value;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The protocol version sent in the handshake, `-1` asks the server to report its own version.
+const STATUS_PROTOCOL_VERSION: i32 = -1;
+/// The handshake state that asks for the server status.
+const STATUS_NEXT_STATE: i32 = 1;
+/// The largest status response accepted, which leaves room for a favicon.
+const MAX_PACKET_LENGTH: i32 = 2 * 1024 * 1024;
+/// The protocol version sent in the legacy ping, the one of Minecraft 1.6.4.
+const LEGACY_PROTOCOL_VERSION: u8 = 78;
+/// The timeout for pinging a server from the API.
+pub const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The status a server reports to the multiplayer server list.
+#[derive(Debug, Clone, Serialize)]
+pub struct PingResponse {
+    /// The message of the day as plain text, without formatting codes.
+    pub motd: String,
+    /// The message of the day as sent by the server, a string or a chat component.
+    pub description: Value,
+    pub version: PingVersion,
+    pub players: PingPlayers,
+    /// The server icon as a `data:image/png;base64,` URL.
+    pub favicon: Option<String>,
+    /// The round trip time of a ping packet in milliseconds, the time of the status request for legacy pings.
+    pub latency_ms: u64,
+    /// Whether the server only answered the legacy ping of Minecraft 1.6 and older.
+    pub legacy: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PingVersion {
+    /// The version name, e.g. `1.21.1` or `Paper 1.21.1`.
+    pub name: String,
+    pub protocol: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PingPlayers {
+    pub online: i64,
+    pub max: i64,
+    /// Some of the online players, servers send at most a dozen and may hide them.
+    pub sample: Vec<PingPlayerSample>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PingPlayerSample {
+    pub name: String,
+    pub id: String,
+}
+
+pub trait ServerListPing {
+    /// Pings the server on localhost, at the readiness port if one is configured and the
+    /// `server-port` from `server.properties` otherwise.
+    fn ping_server(&self) -> Result<PingResponse, Box<dyn Error>>;
+}
+
+impl ServerListPing for Server<u64> {
+    fn ping_server(&self) -> Result<PingResponse, Box<dyn Error>> {
+        let port = self.readiness.port.unwrap_or_else(|| server_port(self));
+        ping("127.0.0.1", port, PING_TIMEOUT)
+    }
+}
+
+/// Pings a Minecraft server, falling back to the legacy ping for servers older than 1.7.
+///
+/// # Arguments
+/// * `host` - The address of the server.
+/// * `port` - The port the server listens on.
+/// * `timeout` - The timeout for connecting and for every read and write.
+pub fn ping(host: &str, port: u16, timeout: Duration) -> Result<PingResponse, Box<dyn Error>> {
+    match ping_modern(host, port, timeout) {
+        Ok(response) => Ok(response),
+        // Old servers close the connection on the handshake, so the error of the modern ping is kept if both fail.
+        Err(e) => legacy_ping(host, port, timeout).or(Err(e)),
+    }
+}
+
+/// Requests the status of a Minecraft server through the Server List Ping protocol.
+///
+/// # Arguments
+/// * `host` - The address of the server.
+/// * `port` - The port the server listens on.
+/// * `timeout` - The timeout for connecting and for every read and write.
+///
+/// # Returns
+/// The status as the JSON string sent by the server.
+pub fn request_status(host: &str, port: u16, timeout: Duration) -> Result<String, Box<dyn Error>> {
+    let mut stream = connect(host, port, timeout)?;
+    read_status(&mut stream, host, port)
+}
+
+/// Requests the status and measures the latency with a ping packet on the same connection.
+fn ping_modern(host: &str, port: u16, timeout: Duration) -> Result<PingResponse, Box<dyn Error>> {
+    let mut stream = connect(host, port, timeout)?;
+    let started = Instant::now();
+    let status = read_status(&mut stream, host, port)?;
+    let status_latency = started.elapsed();
+    let status: Value = serde_json::from_str(&status)?;
+
+    // Some servers close the connection after the status, so a missing pong isn't an error.
+    let latency = measure_latency(&mut stream).unwrap_or(status_latency);
+    let players = &status["players"];
+    Ok(PingResponse {
+        motd: plain_text(&status["description"]),
+        description: status["description"].clone(),
+        version: PingVersion {
+            name: status["version"]["name"].as_str().unwrap_or_default().to_string(),
+            protocol: status["version"]["protocol"].as_i64().unwrap_or_default() as i32,
+        },
+        players: PingPlayers {
+            online: players["online"].as_i64().unwrap_or_default(),
+            max: players["max"].as_i64().unwrap_or_default(),
+            sample: players["sample"]
+                .as_array()
+                .map(|sample| {
+                    sample
+                        .iter()
+                        .map(|player| PingPlayerSample {
+                            name: player["name"].as_str().unwrap_or_default().to_string(),
+                            id: player["id"].as_str().unwrap_or_default().to_string(),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        },
+        favicon: status["favicon"].as_str().map(str::to_string),
+        latency_ms: latency.as_millis() as u64,
+        legacy: false,
+    })
+}
+
+/// Sends a ping packet and waits for the pong carrying the same payload.
+fn measure_latency(stream: &mut TcpStream) -> Result<Duration, Box<dyn Error>> {
+    let payload = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+    let mut ping = vec![0x01];
+    ping.extend_from_slice(&payload.to_be_bytes());
+    let started = Instant::now();
+    write_packet(stream, &ping)?;
+
+    let length = read_var_int(stream)?;
+    if length != 9 {
+        return Err(format!("Invalid pong length: {}", length).into());
+    }
+    let mut pong = [0u8; 9];
+    stream.read_exact(&mut pong)?;
+    let mut echoed = [0u8; 8];
+    echoed.copy_from_slice(&pong[1..]);
+    if pong[0] != 0x01 || i64::from_be_bytes(echoed) != payload {
+        return Err("Unexpected pong".into());
+    }
+    Ok(started.elapsed())
+}
+
+/// Pings a server with the legacy ping of Minecraft 1.6, which servers up to 1.4 answer as well.
+fn legacy_ping(host: &str, port: u16, timeout: Duration) -> Result<PingResponse, Box<dyn Error>> {
+    let mut stream = connect(host, port, timeout)?;
+    let started = Instant::now();
+
+    let channel = utf16_be("MC|PingHost");
+    let host_utf16 = utf16_be(host);
+    let mut request = vec![0xFE, 0x01, 0xFA];
+    request.extend_from_slice(&(channel.len() as u16 / 2).to_be_bytes());
+    request.extend_from_slice(&channel);
+    request.extend_from_slice(&(7 + host_utf16.len() as u16).to_be_bytes());
+    request.push(LEGACY_PROTOCOL_VERSION);
+    request.extend_from_slice(&(host_utf16.len() as u16 / 2).to_be_bytes());
+    request.extend_from_slice(&host_utf16);
+    request.extend_from_slice(&(port as i32).to_be_bytes());
+    stream.write_all(&request)?;
+
+    // The response is a kick packet whose reason is a UTF-16 string of the given number of characters.
+    let mut header = [0u8; 3];
+    stream.read_exact(&mut header)?;
+    if header[0] != 0xFF {
+        return Err(format!("Unexpected legacy ping response: {:#04x}", header[0]).into());
+    }
+    let mut reason = vec![0u8; u16::from_be_bytes([header[1], header[2]]) as usize * 2];
+    stream.read_exact(&mut reason)?;
+    let latency = started.elapsed();
+    let reason = String::from_utf16_lossy(
+        &reason
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect::<Vec<u16>>(),
+    );
+
+    // 1.4 to 1.6 send `§1`, protocol, version, MOTD, online and max players separated by null characters,
+    // older servers send the MOTD, online and max players separated by `§`.
+    let (protocol, version, motd, online, max) = if let Some(fields) = reason.strip_prefix("\u{a7}1\0") {
+        let fields: Vec<&str> = fields.split('\0').collect();
+        if fields.len() < 5 {
+            return Err("Invalid legacy ping response".into());
+        }
+        (
+            fields[0].parse().unwrap_or_default(),
+            fields[1],
+            fields[2],
+            fields[3],
+            fields[4],
+        )
+    } else {
+        let fields: Vec<&str> = reason.rsplitn(3, '\u{a7}').collect();
+        if fields.len() < 3 {
+            return Err("Invalid legacy ping response".into());
+        }
+        (0, "", fields[2], fields[1], fields[0])
+    };
+
+    Ok(PingResponse {
+        motd: strip_formatting(motd),
+        description: Value::String(motd.to_string()),
+        version: PingVersion {
+            name: version.to_string(),
+            protocol,
+        },
+        players: PingPlayers {
+            online: online.parse().unwrap_or_default(),
+            max: max.parse().unwrap_or_default(),
+            sample: Vec::new(),
+        },
+        favicon: None,
+        latency_ms: latency.as_millis() as u64,
+        legacy: true,
+    })
+}
+
+/// Opens a connection with the timeout applied to every read and write.
+fn connect(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, Box<dyn Error>> {
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("Failed to resolve {}", host))?;
+    let stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    Ok(stream)
+}
+
+/// Sends the handshake and the status request and reads the JSON status.
+fn read_status(stream: &mut TcpStream, host: &str, port: u16) -> Result<String, Box<dyn Error>> {
+    let mut handshake = Vec::new();
+    write_var_int(&mut handshake, 0x00);
+    write_var_int(&mut handshake, STATUS_PROTOCOL_VERSION);
+    write_string(&mut handshake, host);
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_var_int(&mut handshake, STATUS_NEXT_STATE);
+    write_packet(stream, &handshake)?;
+    // The status request is an empty packet with the id 0x00.
+    write_packet(stream, &[0x00])?;
+
+    let length = read_var_int(stream)?;
+    if !(1..=MAX_PACKET_LENGTH).contains(&length) {
+        return Err(format!("Invalid status response length: {}", length).into());
+    }
+    let mut packet = vec![0; length as usize];
+    stream.read_exact(&mut packet)?;
+
+    let mut packet = packet.as_slice();
+    let packet_id = read_var_int(&mut packet)?;
+    if packet_id != 0x00 {
+        return Err(format!("Unexpected status response packet: {:#04x}", packet_id).into());
+    }
+    let json_length = read_var_int(&mut packet)?;
+    if json_length < 0 || json_length as usize > packet.len() {
+        return Err("Invalid status response".into());
+    }
+    Ok(String::from_utf8(packet[..json_length as usize].to_vec())?)
+}
+
+/// Flattens a chat component, or a plain string, into text without formatting codes.
+fn plain_text(component: &Value) -> String {
+    fn collect(component: &Value, text: &mut String) {
+        match component {
+            Value::String(value) => text.push_str(value),
+            Value::Array(parts) => parts.iter().for_each(|part| collect(part, text)),
+            Value::Object(object) => {
+                if let Some(value) = object.get("text") {
+                    collect(value, text);
+                }
+                if let Some(extra) = object.get("extra") {
+                    collect(extra, text);
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut text = String::new();
+    collect(component, &mut text);
+    strip_formatting(&text)
+}
+
+/// Removes the `§` formatting codes of legacy chat text.
+fn strip_formatting(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{a7}' {
+            chars.next();
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// Encodes a string as UTF-16 big endian, the string encoding of the legacy protocol.
+pub(crate) fn utf16_be(value: &str) -> Vec<u8> {
+    value.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect()
+}
+
+/// Writes a packet prefixed with its length.
+pub(crate) fn write_packet(stream: &mut impl Write, data: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut packet = Vec::with_capacity(data.len() + 5);
+    write_var_int(&mut packet, data.len() as i32);
+    packet.extend_from_slice(data);
+    stream.write_all(&packet)?;
+    Ok(())
+}
+
+/// Appends a string prefixed with its length in bytes.
+pub(crate) fn write_string(buffer: &mut Vec<u8>, value: &str) {
+    write_var_int(buffer, value.len() as i32);
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+/// Appends a VarInt, which stores 7 bits per byte and uses the high bit to mark that more bytes follow.
+pub(crate) fn write_var_int(buffer: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buffer.push(value as u8);
+            return;
+        }
+        buffer.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+/// Reads a VarInt of at most 5 bytes.
+pub(crate) fn read_var_int(reader: &mut impl Read) -> Result<i32, Box<dyn Error>> {
+    let mut value: u32 = 0;
+    for position in 0..5 {
+        let mut byte = [0u8];
+        reader.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7F) as u32) << (7 * position);
+        if byte[0] & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err("VarInt is too long".into())
+}
@@ -0,0 +1,565 @@
+use crate::console::console_hub;
+use crate::log_events::LogEventKind;
+use crate::rcon::ServerRcon;
+use crate::server::Server;
+use crate::server_database::ServerDatabase;
+use crate::server_process::{is_running, ServerProcess};
+use crate::server_query::ServerQuery;
+use crate::server_status::ServerStatus;
+use database::create_appdb_connection;
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use regex::Regex;
+use serde_derive::Serialize;
+use sqlite::State;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Notify;
+
+/// How often the player tracker checks if the roster is due to be listed or the replayed sessions are to be synced.
+const TRACKER_TICK: Duration = Duration::from_secs(1);
+/// How long to wait for the response to the `list` command.
+const PLAYER_LIST_TIMEOUT: Duration = Duration::from_secs(2);
+/// The response to `list` on vanilla and the loaders built on it.
+const PLAYER_LIST_PATTERN: &str =
+    r"There are (?P<count>\d+) of a max(?: of)? (?P<max>\d+) players online:?(?P<players>.*)$";
+
+/// A player currently on a server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OnlinePlayer {
+    /// The name of the player.
+    pub name: String,
+    /// The UUID of the player, if it was logged when they joined.
+    pub uuid: Option<String>,
+    /// When the player joined, in milliseconds since the Unix epoch.
+    pub joined_at: u64,
+}
+
+/// A stay of a player on a server, from joining to leaving.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerSession {
+    /// The unique identifier of the session.
+    pub id: u64,
+    /// The name of the player.
+    pub player: String,
+    /// The UUID of the player, if it was logged when they joined.
+    pub uuid: Option<String>,
+    /// When the player joined, in milliseconds since the Unix epoch.
+    pub joined_at: u64,
+    /// When the player left, `None` while they're still online.
+    pub left_at: Option<u64>,
+    /// Why the player lost the connection, if it was logged.
+    pub reason: Option<String>,
+    /// The length of the session so far, in seconds.
+    pub duration_seconds: u64,
+}
+
+/// A player that played on a server, with the sessions summed up.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentPlayer {
+    /// The name of the player.
+    pub player: String,
+    /// The UUID of the player, from the latest session that logged it.
+    pub uuid: Option<String>,
+    /// When the player was last on the server, in milliseconds since the Unix epoch.
+    pub last_seen: u64,
+    /// The number of sessions of the player.
+    pub sessions: u64,
+    /// The time spent on the server over all sessions, in seconds.
+    pub total_seconds: u64,
+    /// Whether the player is online right now.
+    pub online: bool,
+}
+
+lazy_static! {
+    /// The players online on each server, by player name.
+    static ref ROSTERS: Mutex<HashMap<u64, HashMap<String, OnlinePlayer>>> = Mutex::new(HashMap::new());
+    /// The servers whose console is followed for players joining and leaving, with the signal that stops following it.
+    static ref TRACKED_SERVERS: Mutex<HashMap<u64, Arc<Notify>>> = Mutex::new(HashMap::new());
+}
+
+/// How often the roster of an online server is checked against the players it lists, in seconds, 0 if never.
+static PLAYER_LIST_INTERVAL_SECONDS: AtomicU64 = AtomicU64::new(300);
+
+/// Sets how often the roster of an online server is checked against the players it lists.
+///
+/// A zero interval turns the periodic check off, the players are then only listed when asked to.
+pub fn set_player_list_interval(interval: Duration) {
+    PLAYER_LIST_INTERVAL_SECONDS.store(interval.as_secs(), Ordering::Relaxed);
+}
+
+/// Creates the `player_session` table if it doesn't already exist.
+///
+/// # Arguments
+///
+/// * `conn` - The connection to the application database.
+pub(crate) fn initialize_player_database(conn: &sqlite::Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `player_session` (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,                       -- Unique identifier for each session
+            server INTEGER NOT NULL,                                    -- ID of the server the player joined
+            player TEXT NOT NULL,                                       -- Name of the player
+            uuid TEXT NULL DEFAULT NULL,                                -- UUID of the player, nullable if it wasn't logged
+            joined_at INTEGER NOT NULL,                                 -- Time the player joined, in milliseconds since the Unix epoch
+            left_at INTEGER NULL DEFAULT NULL,                          -- Time the player left, nullable while the player is online
+            reason TEXT NULL DEFAULT NULL                               -- Reason the player lost the connection, nullable
+        );
+"#,
+    )?;
+    Ok(())
+}
+
+/// Returns the players currently on a server, in the order they joined.
+pub fn online_players(server_id: u64) -> Vec<OnlinePlayer> {
+    let mut players: Vec<OnlinePlayer> = ROSTERS
+        .lock()
+        .map(|rosters| {
+            rosters
+                .get(&server_id)
+                .map(|roster| roster.values().cloned().collect())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default();
+    players.sort_by(|a, b| a.joined_at.cmp(&b.joined_at).then_with(|| a.name.cmp(&b.name)));
+    players
+}
+
+/// Starts following the log events of a server to keep its roster and sessions up to date.
+///
+/// The events come from the console hub, which outlives the process, so a server only needs
+/// to be tracked once, until it's deleted.
+pub(crate) fn track_players(server_id: u64) {
+    let stop = Arc::new(Notify::new());
+    let newly_tracked = TRACKED_SERVERS
+        .lock()
+        .map(|mut tracked| match tracked.entry(server_id) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(Arc::clone(&stop));
+                true
+            }
+        })
+        .unwrap_or(false);
+    if !newly_tracked {
+        return;
+    }
+
+    let hub = console_hub(server_id);
+    let mut events = hub.subscribe_events();
+    thread::spawn(move || {
+        // The tracker may be started outside of a runtime, so it runs on its own.
+        let runtime = match tokio::runtime::Builder::new_current_thread().enable_time().build() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                warn!("Failed to start the player tracker of server {}: {}", server_id, e);
+                return;
+            }
+        };
+        runtime.block_on(async move {
+            let mut tick = tokio::time::interval(TRACKER_TICK);
+            let mut last_list: Option<Instant> = None;
+            // Set by the events read back from the console log of an adopted server, until the sessions are synced.
+            let mut replayed = false;
+            loop {
+                tokio::select! {
+                    _ = stop.notified() => break,
+                    event = events.recv() => match event {
+                        // The sessions of the replayed events may already be stored, they only rebuild the roster.
+                        Ok(event) if event.replayed => {
+                            replayed = true;
+                            match event.kind {
+                                LogEventKind::PlayerJoined { player, uuid } => {
+                                    restore(server_id, player, uuid, event.timestamp)
+                                }
+                                LogEventKind::PlayerLeft { player, .. } => remove_from_roster(server_id, &player),
+                                _ => {}
+                            }
+                        }
+                        Ok(event) => match event.kind {
+                            LogEventKind::PlayerJoined { player, uuid } => {
+                                join(server_id, player, uuid, event.timestamp)
+                            }
+                            LogEventKind::PlayerLeft { player, reason, .. } => {
+                                leave(server_id, &player, reason, event.timestamp)
+                            }
+                            _ => {}
+                        },
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("Player tracker of server {} missed {} events", server_id, skipped)
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    _ = tick.tick() => {
+                        // Every replayed event has been received once the hub is done replaying and none are left.
+                        let caught_up = !hub.is_replaying();
+                        replayed |= !caught_up;
+                        if replayed && caught_up && events.is_empty() {
+                            replayed = false;
+                            sync_sessions(server_id);
+                        }
+                        // Joins and leaves can be missed, so the roster is checked against the server's own
+                        // list every now and then.
+                        let interval = PLAYER_LIST_INTERVAL_SECONDS.load(Ordering::Relaxed);
+                        if caught_up
+                            && interval > 0
+                            && last_list.is_none_or(|last_list| last_list.elapsed().as_secs() >= interval)
+                            && is_online(server_id)
+                        {
+                            last_list = Some(Instant::now());
+                            // Listing the players waits for the server to answer, so it's kept off the runtime.
+                            let listed = tokio::task::spawn_blocking(move || {
+                                Server::get_server(server_id)
+                                    .and_then(|server| refresh_players(&server))
+                                    .map_err(|e| e.to_string())
+                            })
+                            .await
+                            .map_err(|e| e.to_string())
+                            .and_then(|listed| listed);
+                            if let Err(e) = listed {
+                                debug!("Failed to list the players of server {}: {}", server_id, e);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    });
+}
+
+/// Stops following the log events of a server and forgets its roster, used when the server is deleted.
+pub(crate) fn untrack_players(server_id: u64) {
+    if let Some(stop) = TRACKED_SERVERS
+        .lock()
+        .ok()
+        .and_then(|mut tracked| tracked.remove(&server_id))
+    {
+        stop.notify_one();
+    }
+    if let Ok(mut rosters) = ROSTERS.lock() {
+        rosters.remove(&server_id);
+    }
+}
+
+/// Lists the players on a server and updates the roster with them.
+///
+/// The players are asked for over query or RCON if either is enabled, otherwise the `list` command
+/// is run on the console.
+///
+/// # Returns
+/// The players online after the update.
+pub fn refresh_players(server: &Server<u64>) -> Result<Vec<OnlinePlayer>, Box<dyn Error>> {
+    let listed = match list_players_quietly(server)? {
+        Some(listed) => listed,
+        None => {
+            let pattern = Regex::new(PLAYER_LIST_PATTERN)?;
+            let capture = server.send_command_and_capture("list", PLAYER_LIST_TIMEOUT, Some(&pattern))?;
+            parse_player_list(capture.lines.iter().map(|line| line.line.as_str()))?
+        }
+    };
+    update_roster(server.id, &listed);
+    Ok(online_players(server.id))
+}
+
+/// Asks a server for the names of the players online without going through its console.
+///
+/// # Returns
+/// The players from the full query stat, or from the `list` command run over RCON, `None` if neither
+/// query nor RCON is enabled.
+fn list_players_quietly(server: &Server<u64>) -> Result<Option<HashSet<String>>, Box<dyn Error>> {
+    if server.query_settings()?.enabled {
+        return Ok(Some(server.query_server()?.players.into_iter().collect()));
+    }
+    let rcon_settings = server.rcon_settings()?;
+    if rcon_settings.enabled && rcon_settings.password_set {
+        let response = server.send_rcon_command("list")?;
+        return parse_player_list(response.lines()).map(Some);
+    }
+    Ok(None)
+}
+
+/// Finds the response to the `list` command in the given lines and reads the player names from it.
+fn parse_player_list<'a>(lines: impl IntoIterator<Item = &'a str>) -> Result<HashSet<String>, Box<dyn Error>> {
+    let pattern = Regex::new(PLAYER_LIST_PATTERN)?;
+    let captures = lines
+        .into_iter()
+        .find_map(|line| pattern.captures(line))
+        .ok_or("The server didn't answer the list command")?;
+
+    let count: usize = captures["count"].parse()?;
+    let listed: HashSet<String> = captures["players"]
+        .split(',')
+        .map(|player| player.trim().to_string())
+        .filter(|player| !player.is_empty())
+        .collect();
+    // Some servers list the players on the following lines, which can't be told apart from other output.
+    if listed.len() != count {
+        return Err("The list command didn't name every player".into());
+    }
+    Ok(listed)
+}
+
+/// Closes the sessions of the players on the roster that aren't listed and opens the sessions of the
+/// listed players that aren't on it.
+fn update_roster(server_id: u64, listed: &HashSet<String>) {
+    let now = now_millis();
+    let known: HashSet<String> = online_players(server_id)
+        .into_iter()
+        .map(|player| player.name)
+        .collect();
+    for player in known.difference(listed) {
+        leave(server_id, player, None, now);
+    }
+    for player in listed.difference(&known) {
+        join(server_id, player.clone(), None, now);
+    }
+}
+
+/// Ends the sessions of every player on a server, used when the server stops.
+pub(crate) fn end_sessions(server_id: u64) {
+    if let Ok(mut rosters) = ROSTERS.lock() {
+        rosters.remove(&server_id);
+    }
+    let result = create_appdb_connection().and_then(|conn| {
+        let mut statement =
+            conn.prepare("UPDATE player_session SET left_at = ? WHERE server = ? AND left_at IS NULL")?;
+        statement.bind((1, now_millis() as i64))?;
+        statement.bind((2, server_id as i64))?;
+        statement.next()?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        warn!("Failed to end the player sessions of server {}: {}", server_id, e);
+    }
+}
+
+/// Retrieves the most recent sessions on a server, newest first.
+///
+/// # Arguments
+///
+/// * `server_id` - The ID of the server.
+/// * `player` - Only returns the sessions of this player if set.
+/// * `limit` - The maximum number of sessions to return.
+pub fn get_sessions(server_id: u64, player: Option<&str>, limit: u64) -> Result<Vec<PlayerSession>, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare(
+        "SELECT * FROM player_session WHERE server = ? AND (? IS NULL OR player = ?) ORDER BY joined_at DESC LIMIT ?",
+    )?;
+    statement.bind((1, server_id as i64))?;
+    statement.bind((2, player))?;
+    statement.bind((3, player))?;
+    statement.bind((4, limit as i64))?;
+
+    let now = now_millis();
+    let mut sessions = Vec::new();
+    while let State::Row = statement.next()? {
+        let joined_at = statement.read::<i64, _>("joined_at")? as u64;
+        let left_at = statement
+            .read::<Option<i64>, _>("left_at")?
+            .map(|left_at| left_at as u64);
+        sessions.push(PlayerSession {
+            id: statement.read::<i64, _>("id")? as u64,
+            player: statement.read::<String, _>("player")?,
+            uuid: statement.read::<Option<String>, _>("uuid")?,
+            joined_at,
+            left_at,
+            reason: statement.read::<Option<String>, _>("reason")?,
+            duration_seconds: left_at.unwrap_or(now).saturating_sub(joined_at) / 1000,
+        });
+    }
+    Ok(sessions)
+}
+
+/// Retrieves the players that played on a server, most recently seen first.
+///
+/// # Arguments
+///
+/// * `server_id` - The ID of the server.
+/// * `limit` - The maximum number of players to return.
+pub fn get_recent_players(server_id: u64, limit: u64) -> Result<Vec<RecentPlayer>, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let now = now_millis() as i64;
+    let mut statement = conn.prepare(
+        r#"
+        SELECT player,
+               (SELECT uuid FROM player_session latest
+                WHERE latest.server = session.server AND latest.player = session.player AND latest.uuid IS NOT NULL
+                ORDER BY joined_at DESC LIMIT 1) AS uuid,
+               MAX(COALESCE(left_at, ?)) AS last_seen,
+               COUNT(*) AS sessions,
+               SUM(COALESCE(left_at, ?) - joined_at) / 1000 AS total_seconds
+        FROM player_session session
+        WHERE server = ?
+        GROUP BY player
+        ORDER BY last_seen DESC
+        LIMIT ?
+"#,
+    )?;
+    statement.bind((1, now))?;
+    statement.bind((2, now))?;
+    statement.bind((3, server_id as i64))?;
+    statement.bind((4, limit as i64))?;
+
+    let online: HashSet<String> = online_players(server_id)
+        .into_iter()
+        .map(|player| player.name)
+        .collect();
+    let mut players = Vec::new();
+    while let State::Row = statement.next()? {
+        let player = statement.read::<String, _>("player")?;
+        players.push(RecentPlayer {
+            online: online.contains(&player),
+            uuid: statement.read::<Option<String>, _>("uuid")?,
+            last_seen: statement.read::<i64, _>("last_seen")? as u64,
+            sessions: statement.read::<i64, _>("sessions")? as u64,
+            total_seconds: statement.read::<i64, _>("total_seconds")?.max(0) as u64,
+            player,
+        });
+    }
+    Ok(players)
+}
+
+/// Adds a player to the roster and opens their session.
+///
+/// A session left open in the database, e.g. for a server adopted after the panel restarted,
+/// is continued instead of opening a new one.
+fn join(server_id: u64, player: String, uuid: Option<String>, joined_at: u64) {
+    let result = create_appdb_connection().and_then(|conn| {
+        if let Some(joined_at) = open_session(&conn, server_id, &player)? {
+            return Ok(joined_at);
+        }
+
+        let mut statement =
+            conn.prepare("INSERT INTO player_session (server, player, uuid, joined_at) VALUES (?, ?, ?, ?)")?;
+        statement.bind((1, server_id as i64))?;
+        statement.bind((2, player.as_str()))?;
+        statement.bind((3, uuid.as_deref()))?;
+        statement.bind((4, joined_at as i64))?;
+        statement.next()?;
+        Ok(joined_at)
+    });
+    let joined_at = result.unwrap_or_else(|e| {
+        warn!("Failed to record {} joining server {}: {}", player, server_id, e);
+        joined_at
+    });
+    add_to_roster(server_id, player, uuid, joined_at);
+}
+
+/// Adds a player to the roster from a replayed join, continuing their open session without storing anything.
+fn restore(server_id: u64, player: String, uuid: Option<String>, joined_at: u64) {
+    let joined_at = create_appdb_connection()
+        .and_then(|conn| open_session(&conn, server_id, &player))
+        .ok()
+        .flatten()
+        .unwrap_or(joined_at);
+    add_to_roster(server_id, player, uuid, joined_at);
+}
+
+/// Removes a player from the roster and closes their session.
+fn leave(server_id: u64, player: &str, reason: Option<String>, left_at: u64) {
+    remove_from_roster(server_id, player);
+    let result = create_appdb_connection().and_then(|conn| {
+        let mut statement = conn.prepare(
+            "UPDATE player_session SET left_at = ?, reason = ? WHERE server = ? AND player = ? AND left_at IS NULL",
+        )?;
+        statement.bind((1, left_at as i64))?;
+        statement.bind((2, reason.as_deref()))?;
+        statement.bind((3, server_id as i64))?;
+        statement.bind((4, player))?;
+        statement.next()?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        warn!("Failed to record {} leaving server {}: {}", player, server_id, e);
+    }
+}
+
+/// Brings the open sessions in the database in line with the roster rebuilt from the console log of an
+/// adopted server, closing the sessions of players that left while the panel was down and opening the
+/// sessions of players that joined.
+fn sync_sessions(server_id: u64) {
+    let open = create_appdb_connection().and_then(|conn| {
+        let mut statement = conn.prepare("SELECT player FROM player_session WHERE server = ? AND left_at IS NULL")?;
+        statement.bind((1, server_id as i64))?;
+        let mut open = HashSet::new();
+        while let State::Row = statement.next()? {
+            open.insert(statement.read::<String, _>("player")?);
+        }
+        Ok(open)
+    });
+    let open = match open {
+        Ok(open) => open,
+        Err(e) => {
+            warn!("Failed to read the open player sessions of server {}: {}", server_id, e);
+            return;
+        }
+    };
+
+    let online = online_players(server_id);
+    let now = now_millis();
+    for player in open
+        .iter()
+        .filter(|player| !online.iter().any(|online| &online.name == *player))
+    {
+        leave(server_id, player, None, now);
+    }
+    for player in online.into_iter().filter(|player| !open.contains(&player.name)) {
+        join(server_id, player.name, player.uuid, player.joined_at);
+    }
+}
+
+/// Looks up when the open session of a player started, `None` if the player has no open session.
+fn open_session(conn: &sqlite::Connection, server_id: u64, player: &str) -> Result<Option<u64>, sqlite::Error> {
+    let mut statement = conn.prepare(
+        "SELECT joined_at FROM player_session WHERE server = ? AND player = ? AND left_at IS NULL ORDER BY id DESC LIMIT 1",
+    )?;
+    statement.bind((1, server_id as i64))?;
+    statement.bind((2, player))?;
+    Ok(match statement.next()? {
+        State::Row => Some(statement.read::<i64, _>("joined_at")? as u64),
+        State::Done => None,
+    })
+}
+
+/// Adds a player to the roster of a server, replacing the entry of a player with the same name.
+fn add_to_roster(server_id: u64, player: String, uuid: Option<String>, joined_at: u64) {
+    if let Ok(mut rosters) = ROSTERS.lock() {
+        rosters.entry(server_id).or_default().insert(
+            player.clone(),
+            OnlinePlayer {
+                name: player,
+                uuid,
+                joined_at,
+            },
+        );
+    }
+}
+
+/// Removes a player from the roster of a server.
+fn remove_from_roster(server_id: u64, player: &str) {
+    if let Ok(mut rosters) = ROSTERS.lock() {
+        if let Some(roster) = rosters.get_mut(&server_id) {
+            roster.remove(player);
+        }
+    }
+}
+
+/// Checks if a server is running and finished starting, so it can answer commands.
+fn is_online(server_id: u64) -> bool {
+    is_running(server_id)
+        && Server::get_server(server_id).is_ok_and(|server| server.status == Some(ServerStatus::Online))
+}
+
+/// The current time in milliseconds since the Unix epoch.
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
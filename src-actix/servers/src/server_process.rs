@@ -1,17 +1,39 @@
+use crate::cgroup::{prepare_server_cgroup, remove_server_cgroup};
+use crate::console::{console_hub, CommandCapture, ConsoleLine, ConsoleSource};
+use crate::jvm_options::java_major_version;
+use crate::rcon::ServerRcon;
+use crate::readiness::{ReadinessProbe, DEFAULT_READY_PATTERN};
 use crate::server::Server;
+use crate::server_crash::{record_crash, CRASH_CONSOLE_LINES};
 use crate::server_database::ServerDatabase;
+use crate::server_list_ping::ping;
+use crate::server_players::{end_sessions, track_players};
 use crate::server_status::ServerStatus;
+use crate::start_command::render_start_command;
 use crate::start_executable_type::{StartExecutableType, StartExecutableTypeExt};
+use crate::start_validation::{server_port, StartValidation};
+use crate::wake_listener::{start_wake_listener, stop_all_wake_listeners, stop_wake_listener, update_wake_listener};
+use database::create_appdb_connection;
 use lazy_static::lazy_static;
 use log::{debug, info, warn};
+use regex::Regex;
+use serde_derive::Serialize;
 use std::clone::Clone;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
+use std::fs::File;
+use std::io::Write;
 use std::io::{BufRead, Error as IoError};
-use std::io::{Read, Write};
-use std::process::{ChildStdin, ChildStdout, Stdio};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+#[cfg(not(unix))]
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
 
 #[derive(Debug)]
 struct RunningServerProcess {
@@ -19,37 +41,219 @@ struct RunningServerProcess {
     pub server_id: u64,
     /// The process id of the running server
     pub pid: u64,
-    /// Configuration related to the server's standard input stream.
-    pub stdin: Option<ChildStdin>,
-    /// Configuration related to the server's standard output stream.
-    pub stdout: Option<ChildStdout>,
+    /// The server's standard input stream, a FIFO on unix so it can be reopened after the panel restarts.
+    pub stdin: Option<File>,
+    /// Why the panel is shutting the server down, used by the exit watcher to pick the final status.
+    pub exit_intent: ExitIntent,
 }
 
+/// The command a server is started with, as shown before starting it.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandPreview {
+    /// The program that's run, the Java runtime for jars.
+    pub program: String,
+    /// The arguments passed to the program.
+    pub args: Vec<String>,
+    /// The program and its arguments quoted as a shell command line.
+    pub command_line: String,
+    /// The directory the command runs in.
+    pub working_directory: PathBuf,
+    /// The environment variables set for the command, on top of the panel's own.
+    pub environment: BTreeMap<String, String>,
+}
+
+/// The reason a running server is being shut down by the panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitIntent {
+    /// The panel hasn't asked the server to exit, any exit is unexpected.
+    None,
+    /// The server is being stopped and should end up `Offline`.
+    Stop,
+    /// The server is being restarted, the status stays `Restarting` until it's back online.
+    Restart,
+    /// The server is stopped for being idle and should end up `Sleeping`.
+    Sleep,
+}
+
+/// How long `stop_server` waits for the server to exit after sending the `stop` command.
+pub const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_secs(30);
+/// Set once the panel is shutting down, from then on no server is started.
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+/// How long to wait for the process to exit after sending SIGTERM before sending SIGKILL.
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait after the server exited for the console pump to publish its last lines.
+const CONSOLE_DRAIN_DELAY: Duration = Duration::from_millis(500);
+/// How often the TCP and Server List Ping readiness probes are tried.
+const READINESS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long a single readiness probe waits for the server to answer.
+const READINESS_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long a console subscriber waits for a line before it checks if the server is still running.
+const ATTACH_EXIT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// The directory inside the server's directory holding the files that connect the panel to the process.
+const CONSOLE_DIRECTORY: &str = ".obsidian";
+/// The FIFO the server reads its standard input from.
+#[cfg(unix)]
+const STDIN_FIFO: &str = "stdin";
+/// The file the server writes its standard output to.
+#[cfg(unix)]
+const CONSOLE_LOG: &str = "console.log";
+/// The file the server writes its standard error to.
+#[cfg(unix)]
+const STDERR_LOG: &str = "stderr.log";
+/// The file the exit code of the server is written to once it exits.
+const EXIT_CODE_FILE: &str = "exit_code";
+
 lazy_static! {
     static ref RUNNING_SERVERS: Arc<Mutex<Vec<Arc<Mutex<RunningServerProcess>>>>> = Arc::new(Mutex::new(Vec::new()));
+    /// The times of the recent automatic restarts of each server, used to enforce the restart policy window.
+    static ref RESTART_ATTEMPTS: Mutex<HashMap<u64, Vec<Instant>>> = Mutex::new(HashMap::new());
+    /// The servers currently waiting out the backoff before an automatic restart.
+    static ref PENDING_RESTARTS: Mutex<HashSet<u64>> = Mutex::new(HashSet::new());
 }
 
 pub trait ServerProcess {
     fn start_server(&mut self) -> Result<u64, Box<dyn Error>>;
     fn stop_server(&mut self) -> Result<u64, Box<dyn Error>>;
+    /// Stops the server, waiting `timeout` for it to shut down on its own before escalating
+    /// to SIGTERM and then SIGKILL. Returns the pid of the stopped process, or `0` if the server
+    /// was only waiting for an automatic restart, which gets cancelled.
+    fn stop_server_with_timeout(&mut self, timeout: Duration) -> Result<u64, Box<dyn Error>>;
+    /// Stops the server and starts it again with the same settings, keeping the status
+    /// `Restarting` until it's back online. Returns the pid of the new process.
+    fn restart_server(&mut self) -> Result<u64, Box<dyn Error>>;
+    /// Same as `restart_server` but with a custom grace period for the stop.
+    fn restart_server_with_timeout(&mut self, timeout: Duration) -> Result<u64, Box<dyn Error>>;
+    /// Stops the server for being idle. It ends up `Sleeping`, with the panel listening on its port
+    /// to start it again when a player tries to join, if its idle policy wakes it on connect.
+    fn sleep_server(&mut self) -> Result<u64, Box<dyn Error>>;
+    /// Kills the server's process tree right away without letting it save, for servers that
+    /// don't respond or were adopted without a console. Returns the pid of the killed process.
+    fn kill_server(&mut self) -> Result<u64, Box<dyn Error>>;
+    /// Builds the command that starts the server, with its program, arguments and working directory.
+    fn build_command(&self) -> Result<Command, Box<dyn Error>>;
+    /// Describes the exact command `start_server` runs, without starting anything.
+    fn preview_command(&self) -> Result<CommandPreview, Box<dyn Error>>;
+    /// Writes a command to the server's console, falling back to RCON if the panel has no stdin
+    /// for the process, e.g. for a server adopted on a platform without console FIFOs.
     fn send_command_to_server(&self, command: impl AsRef<str>) -> Result<(), Box<dyn Error>>;
+    /// Sends a command and collects the console lines printed afterward, for up to `window` or
+    /// until a line matches `until`, which is included in the capture.
+    fn send_command_and_capture(
+        &self,
+        command: impl AsRef<str>,
+        window: Duration,
+        until: Option<&Regex>,
+    ) -> Result<CommandCapture, Box<dyn Error>>;
     fn get_output(&self) -> Result<String, Box<dyn Error>>;
-    fn attach_to_stdout(&self, on_line: impl FnMut(&str) -> bool + Send + Sync + 'static)
-        -> Result<(), Box<dyn Error>>;
+    /// Follows the output of the running server, calling `on_line` with every line written to
+    /// stdout or stderr until the server exits or `on_line` returns `false`.
+    ///
+    /// The scrollback of the current run is replayed first, so every subscriber sees the console
+    /// from the beginning, up to `CONSOLE_SCROLLBACK_LINES` lines.
+    fn attach_to_console(
+        &self,
+        on_line: impl FnMut(&ConsoleLine) -> bool + Send + Sync + 'static,
+    ) -> Result<(), Box<dyn Error>>;
 }
 
 impl ServerProcess for Server<u64> {
     fn start_server(&mut self) -> Result<u64, Box<dyn Error>> {
-        // Check if the server exists in the RUNNING_SERVERS array
-        if let Ok(servers) = RUNNING_SERVERS.lock() {
-            if servers
-                .iter()
-                .any(|s| s.lock().map(|server| server.server_id == self.id).unwrap_or(false))
-            {
-                return Err("Server already running".into());
+        self.spawn_server(ServerStatus::Starting)
+    }
+
+    fn stop_server(&mut self) -> Result<u64, Box<dyn Error>> {
+        self.stop_server_with_timeout(DEFAULT_STOP_TIMEOUT)
+    }
+
+    fn stop_server_with_timeout(&mut self, timeout: Duration) -> Result<u64, Box<dyn Error>> {
+        // A server waiting for an automatic restart has no process, cancelling the restart stops it.
+        if !is_running(self.id) && cancel_pending_restart(self.id) {
+            info!("Cancelled the pending automatic restart of server {:?}", self.name);
+            update_server_status(self.id, ServerStatus::Offline)?;
+            self.status = Some(ServerStatus::Offline);
+            return Ok(0);
+        }
+
+        // A sleeping server has no process, stopping it only stops listening for players.
+        if !is_running(self.id) && self.status == Some(ServerStatus::Sleeping) {
+            stop_wake_listener(self.id)?;
+            update_server_status(self.id, ServerStatus::Offline)?;
+            self.status = Some(ServerStatus::Offline);
+            return Ok(0);
+        }
+
+        let pid = self.stop_process(timeout, TERMINATE_TIMEOUT, ExitIntent::Stop)?;
+
+        // The exit watcher has already persisted the final status, mirror it locally.
+        self.status = Some(ServerStatus::Offline);
+        self.pid = None;
+
+        Ok(pid)
+    }
+
+    fn sleep_server(&mut self) -> Result<u64, Box<dyn Error>> {
+        let pid = self.stop_process(DEFAULT_STOP_TIMEOUT, TERMINATE_TIMEOUT, ExitIntent::Sleep)?;
+        self.status = Some(ServerStatus::Sleeping);
+        self.pid = None;
+        Ok(pid)
+    }
+
+    fn restart_server(&mut self) -> Result<u64, Box<dyn Error>> {
+        self.restart_server_with_timeout(DEFAULT_STOP_TIMEOUT)
+    }
+
+    fn restart_server_with_timeout(&mut self, timeout: Duration) -> Result<u64, Box<dyn Error>> {
+        // A server that isn't running is simply started, still reporting `Restarting`.
+        if is_running(self.id) {
+            // Don't take down a server that wouldn't come back up. Its own port and heap are freed by stopping it.
+            self.ensure_can_restart()?;
+            self.stop_process(timeout, TERMINATE_TIMEOUT, ExitIntent::Restart)?;
+        }
+
+        // Reload the settings in case they changed while the server was shutting down.
+        let mut server = Server::get_server(self.id)?;
+        match server.spawn_server(ServerStatus::Restarting) {
+            Ok(pid) => {
+                *self = server;
+                Ok(pid)
+            }
+            Err(e) => {
+                warn!("Failed to start server {:?} after restart: {}", self.name, e);
+                update_server_status(self.id, ServerStatus::Offline)?;
+                Err(e)
             }
-            
         }
+    }
+
+    fn kill_server(&mut self) -> Result<u64, Box<dyn Error>> {
+        let pid = set_exit_intent(self.id, ExitIntent::Stop)?;
+        update_server_status(self.id, ServerStatus::Stopping)?;
+        self.status = Some(ServerStatus::Stopping);
+
+        warn!("Killing server {:?} (pid {})", self.name, pid);
+        terminate_process(pid, true)?;
+        if !wait_for_exit(self.id, TERMINATE_TIMEOUT) {
+            return Err(Box::new(IoError::new(
+                std::io::ErrorKind::TimedOut,
+                format!("Failed to kill server process {}", pid),
+            )));
+        }
+
+        self.status = Some(ServerStatus::Offline);
+        self.pid = None;
+        Ok(pid)
+    }
+
+    fn build_command(&self) -> Result<Command, Box<dyn Error>> {
+        // A start command template replaces the command built from the start script.
+        if let Some(template) = &self.start_command {
+            let mut args = render_start_command(self, template)?.into_iter();
+            let program = args.next().ok_or("The start command is empty")?;
+            let mut process = Command::new(program);
+            process.current_dir(&self.directory).args(args).envs(&self.environment);
+            return Ok(process);
+        }
+
         // Clone the `start_script` and unwrap it safely; assumes `start_script` is always `Some`.
         let start_script = &self.start_script;
         let start_script = start_script
@@ -77,7 +281,7 @@ impl ServerProcess for Server<u64> {
             }
             StartExecutableType::Jar => {
                 // Check if Java runtime path is provided, otherwise return an error.
-                if let Some(jr) = &self.java_runtime {
+                if let Some(jr) = self.java_runtime.as_ref().filter(|jr| !jr.as_os_str().is_empty()) {
                     jr.to_str().ok_or_else(|| {
                         Box::new(IoError::new(
                             std::io::ErrorKind::InvalidData,
@@ -101,7 +305,7 @@ impl ServerProcess for Server<u64> {
         };
 
         // Prepare to launch a new process using the determined executable or command.
-        let mut process = std::process::Command::new(program);
+        let mut process = Command::new(program);
 
         // Set the working directory for the process.
         process.current_dir(&self.directory);
@@ -110,11 +314,14 @@ impl ServerProcess for Server<u64> {
         if start_executable_type == StartExecutableType::Script {
             process.arg(start_script);
         } else if start_executable_type == StartExecutableType::Jar {
+            // The structured options come first, so the raw Java arguments can override them.
+            let java_version = self.java_runtime.as_deref().and_then(java_major_version);
+            process.args(self.jvm_options.build_args(self.min_ram, self.max_ram, java_version));
             if let Some(java_arg) = &self.java_arguments {
                 // Split Java arguments into separate tokens and handle errors.
                 match shell_words::split(java_arg) {
                     Ok(args) => process.args(args),
-                    Err(_) => { 
+                    Err(_) => {
                         return Err(Box::new(IoError::new(
                             std::io::ErrorKind::InvalidData,
                             "Invalid Java arguments",
@@ -122,9 +329,6 @@ impl ServerProcess for Server<u64> {
                     }
                 };
             }
-            process.arg(format!("-Xms{}G", self.min_ram));
-            process.arg(format!("-Xmx{}G", self.max_ram));
-            
 
             // Adding the -jar argument and the start script path to the command.
             process.arg("-jar");
@@ -143,6 +347,194 @@ impl ServerProcess for Server<u64> {
             }
         }
 
+        process.envs(&self.environment);
+        Ok(process)
+    }
+
+    fn preview_command(&self) -> Result<CommandPreview, Box<dyn Error>> {
+        let process = self.build_command()?;
+        let program = process.get_program().to_string_lossy().into_owned();
+        let args: Vec<String> = process
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        Ok(CommandPreview {
+            command_line: shell_words::join(std::iter::once(&program).chain(&args)),
+            program,
+            args,
+            working_directory: self.directory.clone(),
+            environment: self.environment.clone(),
+        })
+    }
+
+    fn send_command_to_server(&self, command: impl AsRef<str>) -> Result<(), Box<dyn Error>> {
+        if let Ok(servers) = RUNNING_SERVERS.lock() {
+            let server = servers
+                .iter()
+                .find(|s| s.lock().map(|server| server.server_id == self.id).unwrap_or(false))
+                .ok_or_else(|| IoError::new(std::io::ErrorKind::NotFound, "Server not found"))?;
+
+            if let Ok(mut server) = server.lock() {
+                if let Some(stdin) = &mut server.stdin {
+                    writeln!(stdin, "{}", command.as_ref())?;
+                    return Ok(());
+                }
+            }
+            drop(servers);
+            return if rcon_available(self) {
+                self.send_rcon_command(command).map(|_| ())
+            } else {
+                Err(Box::new(IoError::new(
+                    std::io::ErrorKind::BrokenPipe,
+                    "Stdin not available",
+                )))
+            };
+        }
+
+        Err(Box::new(IoError::new(
+			std::io::ErrorKind::NotFound,
+			"Unknown error has occurred. Please try again later. If the problem persists, please contact the server administrator.",
+		)))
+    }
+
+    fn send_command_and_capture(
+        &self,
+        command: impl AsRef<str>,
+        window: Duration,
+        until: Option<&Regex>,
+    ) -> Result<CommandCapture, Box<dyn Error>> {
+        if !has_stdin(self.id) && rcon_available(self) {
+            // RCON returns the response directly, so there's no console to watch.
+            let response = self.send_rcon_command(command)?;
+            let mut capture = CommandCapture::default();
+            for line in response.lines() {
+                capture.matched = until.is_some_and(|until| until.is_match(line));
+                capture.lines.push(ConsoleLine::new(ConsoleSource::Stdout, line));
+                if capture.matched {
+                    break;
+                }
+            }
+            return Ok(capture);
+        }
+
+        // Subscribe before sending, so the first lines of the response can't be missed.
+        let (_, mut receiver) = console_hub(self.id).subscribe();
+        self.send_command_to_server(command)?;
+
+        let deadline = tokio::time::Instant::now() + window;
+        let mut capture = CommandCapture::default();
+        console_runtime()?.block_on(async {
+            // Waits for each line until the window closes, the timeout ends the capture.
+            while let Ok(line) = tokio::time::timeout_at(deadline, receiver.recv()).await {
+                match line {
+                    Ok(line) => {
+                        let matched = until.is_some_and(|until| until.is_match(&line.line));
+                        capture.lines.push(line);
+                        if matched {
+                            capture.matched = true;
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Command capture of server {} missed {} lines", self.id, skipped)
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        Ok(capture)
+    }
+
+    fn get_output(&self) -> Result<String, Box<dyn Error>> {
+        let (scrollback, _) = console_hub(self.id).subscribe();
+        Ok(scrollback
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<String>>()
+            .join("\n"))
+    }
+
+    fn attach_to_console(
+        &self,
+        mut on_line: impl FnMut(&ConsoleLine) -> bool + Send + Sync + 'static,
+    ) -> Result<(), Box<dyn Error>> {
+        let server_id = self.id;
+        let pid =
+            running_pid(server_id).ok_or_else(|| IoError::new(std::io::ErrorKind::NotFound, "Server not found"))?;
+        let (scrollback, mut receiver) = console_hub(server_id).subscribe();
+        let runtime = console_runtime()?;
+        thread::spawn(move || {
+            for line in &scrollback {
+                if !on_line(line) {
+                    return;
+                }
+            }
+            runtime.block_on(async {
+                loop {
+                    // The hub outlives the process, so a quiet console is checked for the process having exited.
+                    match tokio::time::timeout(ATTACH_EXIT_CHECK_INTERVAL, receiver.recv()).await {
+                        Ok(Ok(line)) => {
+                            if !on_line(&line) {
+                                break;
+                            }
+                        }
+                        Ok(Err(RecvError::Lagged(skipped))) => {
+                            warn!("Console subscriber of server {} missed {} lines", server_id, skipped)
+                        }
+                        Ok(Err(RecvError::Closed)) => break,
+                        Err(_) => {
+                            if !is_process_registered(server_id, pid) {
+                                break;
+                            }
+                        }
+                    }
+                }
+            });
+        });
+        Ok(())
+    }
+}
+
+impl Server<u64> {
+    /// Spawns the server process and registers it in the running servers list.
+    ///
+    /// # Arguments
+    /// * `status` - The status to report until the server finishes starting.
+    fn spawn_server(&mut self, status: ServerStatus) -> Result<u64, Box<dyn Error>> {
+        if SHUTTING_DOWN.load(Ordering::SeqCst) {
+            return Err("The panel is shutting down".into());
+        }
+        // Check if the server exists in the RUNNING_SERVERS array
+        if let Ok(servers) = RUNNING_SERVERS.lock() {
+            if servers
+                .iter()
+                .any(|s| s.lock().map(|server| server.server_id == self.id).unwrap_or(false))
+            {
+                return Err("Server already running".into());
+            }
+            
+        }
+        // A sleeping server's port is held by the panel until the server starts.
+        let was_listening = stop_wake_listener(self.id)?;
+        let result = self.spawn_process(status);
+        // A sleeping server that failed to start, e.g. on a validation error, still wakes up once it's fixed.
+        if result.is_err() && was_listening && !is_running(self.id) {
+            if let Err(e) = start_wake_listener(self) {
+                warn!("Failed to listen for players of server {:?} again: {}", self.name, e);
+            }
+        }
+        result
+    }
+
+    /// Validates the server, then spawns its process and registers it in the running servers list.
+    ///
+    /// # Arguments
+    /// * `status` - The status to report until the server finishes starting.
+    fn spawn_process(&mut self, status: ServerStatus) -> Result<u64, Box<dyn Error>> {
+        // Catch problems like a taken port or a missing Java runtime before the process dies on them.
+        self.ensure_can_start()?;
+        let process = self.build_command()?;
+
         info!(
             "Running command: {} {}",
             process.get_program().to_str().unwrap_or("unknown.exe"),
@@ -153,9 +545,48 @@ impl ServerProcess for Server<u64> {
                 .join(" ")
         );
 
-        // Configure the process to provide input/output via pipes.
-        process.stdin(Stdio::piped());
-        process.stdout(Stdio::piped());
+        // Place the server in its own cgroup to enforce its resource limits, if cgroups are available.
+        #[cfg(unix)]
+        let cgroup = match prepare_server_cgroup(self.id, &self.resource_limits) {
+            Ok(cgroup) => Some(cgroup),
+            Err(e) if self.resource_limits.is_unlimited() => {
+                debug!("Starting server {:?} without a cgroup: {}", self.name, e);
+                None
+            }
+            Err(e) => {
+                warn!("Starting server {:?} without its resource limits: {}", self.name, e);
+                None
+            }
+        };
+        #[cfg(not(unix))]
+        if !self.resource_limits.is_unlimited() {
+            warn!(
+                "Starting server {:?} without its resource limits, they are only supported on Linux",
+                self.name
+            );
+        }
+
+        // Record the exit code in the server's directory, so it's known even if the panel restarted in between.
+        #[cfg(unix)]
+        let mut process = record_exit_code(process, cgroup.as_deref());
+
+        // On unix the console goes through a FIFO and a log file, so the server outlives the panel and can be
+        // adopted again after a restart. Other platforms use pipes, which close when the panel exits.
+        #[cfg(unix)]
+        let stdin = open_console(&self.directory, &mut process)?;
+        #[cfg(not(unix))]
+        let mut process = process;
+        #[cfg(not(unix))]
+        {
+            process.stdin(Stdio::piped());
+            process.stdout(Stdio::piped());
+            process.stderr(Stdio::piped());
+        }
+
+        // Run the server in its own process group so a script wrapper and the java process
+        // it launches can be signaled together.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut process, 0);
 
         // Spawn the process and handle potential spawning errors.
         let mut child = process.spawn()?;
@@ -163,177 +594,950 @@ impl ServerProcess for Server<u64> {
         // Retrieve and return the process ID (PID) as a 64-bit integer.
         let pid = child.id();
 
+        #[cfg(unix)]
+        let streams = open_console_logs(&self.directory)?;
+        #[cfg(not(unix))]
+        let (stdin, streams) = {
+            let mut streams = Vec::new();
+            if let Some(stdout) = child.stdout.take() {
+                streams.push((ConsoleSource::Stdout, pipe_to_file(stdout)));
+            }
+            if let Some(stderr) = child.stderr.take() {
+                streams.push((ConsoleSource::Stderr, pipe_to_file(stderr)));
+            }
+            (child.stdin.take().map(pipe_to_file), streams)
+        };
+
         // Add server to the running servers list.
-        match RUNNING_SERVERS.lock() {
-            Ok(mut servers) => servers.push(Arc::new(Mutex::new(RunningServerProcess {
-                server_id: self.id,
-                pid: pid as u64,
-                stdin: child.stdin.take(),
-                stdout: child.stdout.take(),
-            }))),
-            Err(_) => {
-                return Err(Box::new(IoError::new(
-                    std::io::ErrorKind::Other,
-                    "Failed to lock running servers",
-                )))
+        register_process(self.id, pid as u64, stdin, ExitIntent::None)?;
+
+        // The scrollback only holds the console of the current run.
+        console_hub(self.id).clear();
+        track_players(self.id);
+        start_console_pump(self.id, pid as u64, streams, false);
+
+        update_server_status(self.id, status.clone())?;
+        self.status = Some(status);
+        self.pid = Some(pid as u64);
+
+        let server_id = self.id;
+        let server_name = self.name.clone();
+        thread::spawn(move || match child.wait() {
+            Ok(status) => {
+                info!("Server {:?} exited with status: {}", &server_name, status);
+                on_process_exit(server_id, status.code(), status.success());
             }
+            Err(e) => warn!("Failed to wait for server {:?} to exit: {}", &server_name, e),
+        });
+        self.detect_ready(false)?;
+
+        Ok(pid as u64)
+    }
+
+    /// Asks the running server to exit, escalating to SIGTERM and SIGKILL if it doesn't within `timeout`.
+    ///
+    /// # Arguments
+    /// * `timeout` - The grace period after sending `stop`.
+    /// * `terminate_timeout` - How long to wait after SIGTERM, and again after SIGKILL.
+    /// * `intent` - Why the server is being shut down, which decides the status written by the exit watcher.
+    fn stop_process(
+        &mut self,
+        timeout: Duration,
+        terminate_timeout: Duration,
+        intent: ExitIntent,
+    ) -> Result<u64, Box<dyn Error>> {
+        // Flag the running process as stopping and grab its pid.
+        let pid = set_exit_intent(self.id, intent)?;
+
+        let status = if intent == ExitIntent::Restart {
+            ServerStatus::Restarting
+        } else {
+            ServerStatus::Stopping
+        };
+        update_server_status(self.id, status.clone())?;
+        self.status = Some(status);
+
+        // Ask the server to shut down on its own first so the world gets saved.
+        info!("Stopping server {:?} (pid {})", self.name, pid);
+        if let Err(e) = self.send_command_to_server("stop") {
+            warn!("Failed to send stop command to server {:?}: {}", self.name, e);
         }
 
-        let mut server_copy = self.clone();
-        thread::spawn(move || {
-            // Run a loop while the child process is alive.
-            loop {
-                if let Ok(status) = child.wait() {
-                    // Exit loop if the process has terminated.
-                    info!("Server {:?} exited with status: {}", &server_copy.name, status);
-                    // remove server from running_server list
-                    if let Ok(mut servers) = RUNNING_SERVERS.lock() {
-                        debug!(
-                            "Removed server with id of {} from the running server list!",
-                            server_copy.id
-                        );
-                        servers.retain(|s| s.lock().map_or(true, |server| server.server_id != server_copy.id));
-                    }
+        if !wait_for_exit(self.id, timeout) {
+            warn!(
+                "Server {:?} did not stop within {:?}, sending SIGTERM",
+                self.name, timeout
+            );
+            terminate_process(pid, false)?;
+
+            if !wait_for_exit(self.id, terminate_timeout) {
+                warn!("Server {:?} did not respond to SIGTERM, sending SIGKILL", self.name);
+                terminate_process(pid, true)?;
+
+                if !wait_for_exit(self.id, terminate_timeout) {
+                    return Err(Box::new(IoError::new(
+                        std::io::ErrorKind::TimedOut,
+                        format!("Failed to stop server process {}", pid),
+                    )));
+                }
+            }
+        }
+
+        Ok(pid)
+    }
+
+    /// Watches the server until it finished starting and marks it `Online`, or `FailedToStart`
+    /// if it isn't ready within the timeout of its readiness check.
+    ///
+    /// # Arguments
+    /// * `ready` - Whether the server already finished starting, in which case there's nothing to detect.
+    fn detect_ready(&self, ready: bool) -> Result<(), Box<dyn Error>> {
+        if ready {
+            return Ok(());
+        }
+        let server_id = self.id;
+        let pid =
+            running_pid(server_id).ok_or_else(|| IoError::new(std::io::ErrorKind::NotFound, "Server not found"))?;
+        let probe = self.readiness.resolve_probe(self.loader_type);
+        let port = self.readiness.port.unwrap_or_else(|| server_port(self));
+        let deadline = Instant::now() + Duration::from_secs(self.readiness.timeout_seconds);
+        // Set by whichever of the probe and the timeout decides the outcome first.
+        let settled = Arc::new(AtomicBool::new(false));
+
+        if probe == ReadinessProbe::ConsolePattern {
+            let pattern = Regex::new(self.readiness.pattern()).or_else(|e| {
+                warn!(
+                    "Invalid ready pattern of server {:?}, using the default: {}",
+                    self.name, e
+                );
+                Regex::new(DEFAULT_READY_PATTERN)
+            })?;
+            let settled = settled.clone();
+            self.attach_to_console(move |line| {
+                if settled.load(Ordering::SeqCst) {
+                    return false;
+                }
+                if pattern.is_match(&line.line) {
+                    settle_start(server_id, &settled, ServerStatus::Online);
+                    return false;
+                }
+                true
+            })?;
+        }
 
-                    server_copy.status = if status.success() {
-                        Some(ServerStatus::Offline)
-                    } else {
-                        Some(ServerStatus::Crashed)
-                    };
-                    if let Err(e) = server_copy.update() {
-                        warn!("Failed to update server status: {}", e);
+        let server_name = self.name.clone();
+        thread::spawn(move || {
+            let address = SocketAddr::from(([127, 0, 0, 1], port));
+            while !settled.load(Ordering::SeqCst) && is_process_registered(server_id, pid) {
+                let ready = match probe {
+                    ReadinessProbe::TcpPort => TcpStream::connect_timeout(&address, READINESS_PROBE_TIMEOUT).is_ok(),
+                    ReadinessProbe::ServerListPing => ping("127.0.0.1", port, READINESS_PROBE_TIMEOUT).is_ok(),
+                    // The console is followed by its own subscriber.
+                    ReadinessProbe::ConsolePattern | ReadinessProbe::Loader => false,
+                };
+                if ready {
+                    settle_start(server_id, &settled, ServerStatus::Online);
+                    return;
+                }
+                if Instant::now() >= deadline {
+                    if settle_start(server_id, &settled, ServerStatus::FailedToStart) {
+                        warn!("Server {:?} didn't become ready in time", server_name);
                     }
-                    break;
+                    return;
                 }
-                // Add a small delay to prevent high CPU usage.
-                thread::sleep(Duration::from_millis(1000));
+                thread::sleep(READINESS_POLL_INTERVAL);
             }
         });
-        let mut server_copy = self.clone();
-        self.attach_to_stdout(move |line| {
-            if line.contains("Done") && line.contains(r#"For help, type "help""#) {
-                server_copy.status = Some(ServerStatus::Online);
+        Ok(())
+    }
 
-                if let Err(e) = server_copy.update() {
-                    warn!("Failed to update server status: {}", e);
-                }
+    /// Registers a server process that kept running while the panel was down and follows it again.
+    ///
+    /// The console logs are read from the beginning, so the scrollback and the `Online` detection
+    /// pick up where the previous panel left off.
+    ///
+    /// # Arguments
+    /// * `pid` - The process id of the running server.
+    #[cfg(unix)]
+    fn adopt_process(&mut self, pid: u64) -> Result<(), Box<dyn Error>> {
+        use nix::fcntl::{fcntl, FcntlArg, OFlag};
+        use std::os::fd::AsRawFd;
+        use std::os::unix::fs::OpenOptionsExt;
 
-                return false;
+        let console_directory = self.directory.join(CONSOLE_DIRECTORY);
+
+        // Opening the FIFO without blocking fails if nobody reads it, e.g. for a server started by an
+        // older version of the panel, in which case the server is adopted without a console input.
+        let stdin = std::fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(OFlag::O_NONBLOCK.bits())
+            .open(console_directory.join(STDIN_FIFO))
+            .ok();
+        if let Some(stdin) = &stdin {
+            fcntl(stdin.as_raw_fd(), FcntlArg::F_SETFL(OFlag::empty()))?;
+        }
+
+        let status = self.status.clone().unwrap_or_default();
+        let intent = if status == ServerStatus::Stopping {
+            ExitIntent::Stop
+        } else {
+            ExitIntent::None
+        };
+        register_process(self.id, pid, stdin, intent)?;
+        info!("Adopted server {:?} (pid {})", self.name, pid);
+
+        // The console logs are read back from the beginning, so the player tracker must know it's replaying
+        // before it starts.
+        let streams = open_console_logs(&self.directory)?;
+        let hub = console_hub(self.id);
+        hub.clear();
+        hub.start_replay();
+        track_players(self.id);
+        start_console_pump(self.id, pid, streams, true);
+
+        // The process isn't a child of this panel, so its exit is noticed by polling and
+        // the exit code is read from the file written by the wrapper.
+        let server_id = self.id;
+        let server_name = self.name.clone();
+        let directory = self.directory.clone();
+        thread::spawn(move || {
+            while process_exists(pid) {
+                thread::sleep(Duration::from_secs(1));
             }
-            true
-        })?;
+            let exit_code = read_exit_code(&directory);
+            info!("Server {:?} exited with code: {:?}", &server_name, exit_code);
+            on_process_exit(server_id, exit_code, exit_code == Some(0));
+        });
 
-        self.status = Some(ServerStatus::Starting);
-        self.update()?;
+        self.detect_ready(!matches!(status, ServerStatus::Starting | ServerStatus::Restarting))
+    }
+}
 
-        Ok(pid as u64)
+/// Adds a server process to the running servers list.
+fn register_process(
+    server_id: u64,
+    pid: u64,
+    stdin: Option<File>,
+    exit_intent: ExitIntent,
+) -> Result<(), Box<dyn Error>> {
+    match RUNNING_SERVERS.lock() {
+        Ok(mut servers) => {
+            servers.push(Arc::new(Mutex::new(RunningServerProcess {
+                server_id,
+                pid,
+                stdin,
+                exit_intent,
+            })));
+            Ok(())
+        }
+        Err(_) => Err(Box::new(IoError::new(
+            std::io::ErrorKind::Other,
+            "Failed to lock running servers",
+        ))),
     }
+}
 
-    fn stop_server(&mut self) -> Result<u64, Box<dyn Error>> {
-        self.status = Some(ServerStatus::Stopping);
-        self.update()?;
-        todo!()
+/// Removes an exited server process from the running servers list and writes its final status.
+///
+/// # Arguments
+/// * `server_id` - The id of the server that exited.
+/// * `exit_code` - The exit code of the process, if known.
+/// * `success` - Whether the process exited successfully.
+fn on_process_exit(server_id: u64, exit_code: Option<i32>, success: bool) {
+    // remove server from running_server list
+    let mut exit_intent = ExitIntent::None;
+    if let Ok(mut servers) = RUNNING_SERVERS.lock() {
+        debug!("Removed server with id of {} from the running server list!", server_id);
+        servers.retain(|s| {
+            s.lock().map_or(true, |server| {
+                if server.server_id == server_id {
+                    exit_intent = server.exit_intent;
+                    false
+                } else {
+                    true
+                }
+            })
+        });
     }
 
-    fn send_command_to_server(&self, command: impl AsRef<str>) -> Result<(), Box<dyn Error>> {
-        if let Ok(servers) = RUNNING_SERVERS.lock() {
-            let server = servers
-                .iter()
-                .find(|s| s.lock().map(|server| server.server_id == self.id).unwrap_or(false))
-                .ok_or_else(|| IoError::new(std::io::ErrorKind::NotFound, "Server not found"))?;
+    remove_server_cgroup(server_id);
+    end_sessions(server_id);
 
-            if let Ok(mut server) = server.lock() {
-                return if let Some(stdin) = &mut server.stdin {
-                    writeln!(stdin, "{}", command.as_ref())?;
-                    Ok(())
-                } else {
-                    Err(Box::new(IoError::new(
-                        std::io::ErrorKind::BrokenPipe,
-                        "Stdin not available",
-                    )))
-                };
+    match exit_intent {
+        // The restart takes care of the status once the server is back up.
+        ExitIntent::Restart => {}
+        ExitIntent::Stop => {
+            if let Err(e) = update_server_status(server_id, ServerStatus::Offline) {
+                warn!("Failed to update server status: {}", e);
             }
         }
+        ExitIntent::Sleep => {
+            if let Err(e) = update_server_status(server_id, ServerStatus::Sleeping) {
+                warn!("Failed to update server status: {}", e);
+            }
+            // Listens for players if the idle policy wakes the server on connect.
+            if let Err(e) = Server::get_server(server_id).and_then(|server| update_wake_listener(&server)) {
+                warn!("Failed to listen for players of sleeping server {}: {}", server_id, e);
+            }
+        }
+        ExitIntent::None => {
+            // Give the console pump a moment to publish the last lines printed before the exit.
+            thread::sleep(CONSOLE_DRAIN_DELAY);
+            let console = console_hub(server_id)
+                .recent(CRASH_CONSOLE_LINES)
+                .iter()
+                .map(|line| line.to_string())
+                .collect();
+            handle_unexpected_exit(server_id, exit_code, success, console);
+        }
+    }
+}
 
-        Err(Box::new(IoError::new(
-			std::io::ErrorKind::NotFound,
-			"Unknown error has occurred. Please try again later. If the problem persists, please contact the server administrator.",
-		)))
+/// Flags the running process of a server with the reason it's being shut down.
+///
+/// # Returns
+/// The pid of the running process.
+fn set_exit_intent(server_id: u64, intent: ExitIntent) -> Result<u64, Box<dyn Error>> {
+    let servers = RUNNING_SERVERS
+        .lock()
+        .map_err(|_| IoError::new(std::io::ErrorKind::Other, "Failed to lock running servers"))?;
+    let server = servers
+        .iter()
+        .find(|s| s.lock().map(|server| server.server_id == server_id).unwrap_or(false))
+        .ok_or_else(|| IoError::new(std::io::ErrorKind::NotFound, "Server is not running"))?;
+    let mut server = server
+        .lock()
+        .map_err(|_| IoError::new(std::io::ErrorKind::Other, "Failed to lock running server"))?;
+    server.exit_intent = intent;
+    Ok(server.pid)
+}
+
+/// Builds the runtime a blocking caller waits for console lines on, so it can give up after a timeout.
+fn console_runtime() -> Result<tokio::runtime::Runtime, Box<dyn Error>> {
+    Ok(tokio::runtime::Builder::new_current_thread().enable_time().build()?)
+}
+
+/// Returns the pid of the server's running process, if it's in the running servers list.
+pub(crate) fn running_pid(server_id: u64) -> Option<u64> {
+    RUNNING_SERVERS.lock().ok().and_then(|servers| {
+        servers.iter().find_map(|s| {
+            s.lock()
+                .ok()
+                .filter(|server| server.server_id == server_id)
+                .map(|server| server.pid)
+        })
+    })
+}
+
+/// Returns whether the server with the given id is in the running servers list.
+pub(crate) fn is_running(server_id: u64) -> bool {
+    running_pid(server_id).is_some()
+}
+
+/// Returns whether the panel holds the stdin of the server's process.
+fn has_stdin(server_id: u64) -> bool {
+    RUNNING_SERVERS
+        .lock()
+        .map(|servers| {
+            servers.iter().any(|s| {
+                s.lock()
+                    .map(|server| server.server_id == server_id && server.stdin.is_some())
+                    .unwrap_or(false)
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Returns whether commands can be sent to the server over RCON instead.
+fn rcon_available(server: &Server<u64>) -> bool {
+    server
+        .rcon_settings()
+        .is_ok_and(|settings| settings.enabled && settings.password_set)
+}
+
+/// Returns whether the given process of the server is still in the running servers list.
+fn is_process_registered(server_id: u64, pid: u64) -> bool {
+    running_pid(server_id) == Some(pid)
+}
+
+/// Waits up to `timeout` for the exit watcher to remove the server from the running servers list.
+///
+/// # Returns
+/// `true` if the server exited within the timeout, `false` otherwise.
+fn wait_for_exit(server_id: u64, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    while is_running(server_id) {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(250));
     }
+    true
+}
 
-    fn get_output(&self) -> Result<String, Box<dyn Error>> {
-        return if let Ok(servers) = RUNNING_SERVERS.lock() {
-            let server = servers
-                .iter()
-                .find(|s| s.lock().map(|server| server.server_id == self.id).unwrap_or(false))
-                .ok_or_else(|| IoError::new(std::io::ErrorKind::NotFound, "Server not found"))?;
+/// Sends a termination signal to the server's process group.
+///
+/// # Arguments
+/// * `pid` - The process id of the server, which is also its process group id.
+/// * `force` - Sends SIGKILL when `true`, otherwise SIGTERM.
+#[cfg(unix)]
+fn terminate_process(pid: u64, force: bool) -> Result<(), Box<dyn Error>> {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
 
-            if let Ok(mut server) = server.lock() {
-                println!("Server: {:?}", server);
-                if let Some(stdout) = &mut server.stdout {
-                    let mut output = String::new();
-                    stdout.read_to_string(&mut output)?;
-                    Ok(output)
-                } else {
-                    Err(Box::new(IoError::new(
-                        std::io::ErrorKind::NotFound,
-                        "Stdout not available",
-                    )))
-                }
-            } else {
-                Err(Box::new(IoError::new(
-					std::io::ErrorKind::NotFound,
-					"Failed to access the individual server object, this might mean its being locked by another thread. Please try again later. If the problem persists, please contact the server administrator.",
-				)))
-            }
-        } else {
-            Err(Box::new(IoError::new(
-				std::io::ErrorKind::NotFound,
-				"Failed to access the running servers array, this might mean its being locked by another thread. Please try again later. If the problem persists, please contact the server administrator.",
-			)))
+    let signal = if force { Signal::SIGKILL } else { Signal::SIGTERM };
+    match killpg(Pid::from_raw(pid as i32), signal) {
+        // The process already exited between the checks.
+        Ok(_) | Err(nix::errno::Errno::ESRCH) => Ok(()),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+/// Terminates the server's process tree using `taskkill`.
+///
+/// # Arguments
+/// * `pid` - The process id of the server.
+/// * `force` - Forcefully terminates the process when `true`.
+#[cfg(not(unix))]
+fn terminate_process(pid: u64, force: bool) -> Result<(), Box<dyn Error>> {
+    let mut command = std::process::Command::new("taskkill");
+    command.arg("/T").arg("/PID").arg(pid.to_string());
+    if force {
+        command.arg("/F");
+    }
+    command.stdout(Stdio::null()).stderr(Stdio::null()).status()?;
+    Ok(())
+}
+
+/// Wraps the command in `sh`, which writes the exit code of the server to the console directory once it exits.
+///
+/// The server runs in the background of the wrapper with its standard input passed through, and the wrapper
+/// ignores the termination signals sent to the process group so it's still around to record the exit code.
+/// With a cgroup, the wrapper moves itself into it first, so the server and everything it starts end up there.
+#[cfg(unix)]
+fn record_exit_code(command: Command, cgroup: Option<&Path>) -> Command {
+    let join_cgroup = cgroup
+        .map(|cgroup| format!("echo $$ > '{}/cgroup.procs'; ", cgroup.display()))
+        .unwrap_or_default();
+    let mut wrapper = Command::new("sh");
+    wrapper
+        .arg("-c")
+        .arg(format!(
+            r#"{}exec 3<&0; "$@" <&3 3<&- & trap "" INT TERM HUP; wait $!; code=$?; echo $code > {}/{}; exit $code"#,
+            join_cgroup, CONSOLE_DIRECTORY, EXIT_CODE_FILE
+        ))
+        .arg("sh")
+        .arg(command.get_program())
+        .args(command.get_args());
+    for (name, value) in command.get_envs() {
+        match value {
+            Some(value) => wrapper.env(name, value),
+            None => wrapper.env_remove(name),
         };
     }
+    if let Some(directory) = command.get_current_dir() {
+        wrapper.current_dir(directory);
+    }
+    wrapper
+}
 
-    fn attach_to_stdout(
-        &self,
-        on_line: impl FnMut(&str) -> bool + Send + Sync + 'static,
-    ) -> Result<(), Box<dyn Error>> {
-        if let Ok(servers) = RUNNING_SERVERS.lock() {
-            let server = servers
-                .iter()
-                .find(|s| s.lock().map(|server| server.server_id == self.id).unwrap_or(false))
-                .ok_or_else(|| IoError::new(std::io::ErrorKind::NotFound, "Server not found"))?;
+/// Creates the stdin FIFO and the console logs in the server's console directory and connects them to the process.
+///
+/// # Returns
+/// The panel's end of the console input, a writer to the FIFO.
+#[cfg(unix)]
+fn open_console(directory: &Path, process: &mut Command) -> Result<Option<File>, Box<dyn Error>> {
+    use nix::sys::stat::Mode;
+    use std::fs::OpenOptions;
+    use std::os::unix::fs::FileTypeExt;
 
-            if let Ok(mut server) = server.lock() {
-                if let Some(stdout) = server.stdout.take() {
-                    let on_line = Arc::new(Mutex::new(on_line));
-                    thread::spawn(move || {
-                        let on_line = Arc::clone(&on_line);
-                        let mut reader = std::io::BufReader::new(stdout);
-                        let mut buffer = String::new();
-
-                        loop {
-                            buffer.clear();
-                            match reader.read_line(&mut buffer) {
-                                Ok(0) => break, // EOF reached
-                                Ok(_) => {
-                                    if let Ok(mut callback) = on_line.lock() {
-                                        let should_continue = callback(buffer.trim_end());
-                                        if !should_continue {
-                                            break;
-                                        }
-                                    }
-                                }
-                                Err(err) => {
-                                    warn!("Error reading stdout: {}", err);
-                                    break;
-                                }
+    let console_directory = directory.join(CONSOLE_DIRECTORY);
+    std::fs::create_dir_all(&console_directory)?;
+    // Left over from the previous run of the server
+    if let Err(e) = std::fs::remove_file(console_directory.join(EXIT_CODE_FILE)) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            return Err(Box::new(e));
+        }
+    }
+
+    let fifo = console_directory.join(STDIN_FIFO);
+    let is_fifo = std::fs::metadata(&fifo)
+        .map(|metadata| metadata.file_type().is_fifo())
+        .unwrap_or(false);
+    if !is_fifo {
+        if fifo.exists() {
+            std::fs::remove_file(&fifo)?;
+        }
+        nix::unistd::mkfifo(&fifo, Mode::S_IRUSR | Mode::S_IWUSR)?;
+    }
+    // Opening the FIFO for reading and writing doesn't block, and since the server holds a writer
+    // itself, its input isn't closed when the panel goes away.
+    let child_stdin = OpenOptions::new().read(true).write(true).open(&fifo)?;
+    let stdin = OpenOptions::new().write(true).open(&fifo)?;
+
+    let console_log = console_directory.join(CONSOLE_LOG);
+    File::create(&console_log)?;
+    let child_stdout = OpenOptions::new().append(true).open(&console_log)?;
+
+    let stderr_log = console_directory.join(STDERR_LOG);
+    File::create(&stderr_log)?;
+    let child_stderr = OpenOptions::new().append(true).open(&stderr_log)?;
+
+    process.stdin(child_stdin);
+    process.stdout(child_stdout);
+    process.stderr(child_stderr);
+    Ok(Some(stdin))
+}
+
+/// Opens the console logs of the server for reading, from the beginning.
+#[cfg(unix)]
+fn open_console_logs(directory: &Path) -> Result<Vec<(ConsoleSource, File)>, Box<dyn Error>> {
+    let console_directory = directory.join(CONSOLE_DIRECTORY);
+    let mut streams = vec![(ConsoleSource::Stdout, File::open(console_directory.join(CONSOLE_LOG))?)];
+    // Servers adopted from an older version of the panel have no stderr log
+    if let Ok(stderr) = File::open(console_directory.join(STDERR_LOG)) {
+        streams.push((ConsoleSource::Stderr, stderr));
+    }
+    Ok(streams)
+}
+
+/// Reads the output streams of a server process and publishes every line to the server's console hub.
+///
+/// On unix the streams are the console logs, which keep growing while the process runs, so they
+/// are followed until the process is removed from the running servers list and then read to the end.
+///
+/// # Arguments
+/// * `server_id` - The id of the server.
+/// * `pid` - The process id the streams belong to.
+/// * `streams` - The output streams of the process.
+/// * `replay` - Whether the hub is replaying the streams of an adopted server, which is finished once the
+///   standard output has been read up to where it ended when the pump started.
+fn start_console_pump(server_id: u64, pid: u64, streams: Vec<(ConsoleSource, File)>, replay: bool) {
+    for (source, stream) in streams {
+        // Only the standard output is parsed into log events.
+        let mut replaying = replay && source == ConsoleSource::Stdout;
+        let replay_end = match replaying {
+            true => stream.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            false => 0,
+        };
+        thread::spawn(move || {
+            let hub = console_hub(server_id);
+            let mut reader = std::io::BufReader::new(stream);
+            let mut buffer = String::new();
+            let mut read: u64 = 0;
+            // Set once the process is gone, the rest of the output is read before stopping.
+            let mut exited = false;
+
+            loop {
+                if replaying && read >= replay_end {
+                    hub.finish_replay();
+                    replaying = false;
+                }
+                match reader.read_line(&mut buffer) {
+                    // Nothing new yet, the console log keeps growing while the process runs.
+                    Ok(0) => {
+                        if exited {
+                            if !buffer.is_empty() {
+                                hub.publish(ConsoleLine::new(source, buffer.trim_end()));
                             }
+                            break;
                         }
-                    });
+                        exited = !is_process_registered(server_id, pid);
+                        if !exited {
+                            thread::sleep(Duration::from_millis(100));
+                        }
+                    }
+                    // Wait for the rest of a line that is still being written.
+                    Ok(length) if !buffer.ends_with('\n') => read += length as u64,
+                    Ok(length) => {
+                        read += length as u64;
+                        hub.publish(ConsoleLine::new(source, buffer.trim_end()));
+                        buffer.clear();
+                    }
+                    Err(err) => {
+                        warn!("Error reading {:?}: {}", source, err);
+                        break;
+                    }
                 }
             }
+            if replaying {
+                hub.finish_replay();
+            }
+        });
+    }
+}
+
+/// Converts a pipe of the child process into a file so it can be stored alongside the unix console files.
+#[cfg(windows)]
+fn pipe_to_file(pipe: impl Into<std::os::windows::io::OwnedHandle>) -> File {
+    File::from(pipe.into())
+}
+
+/// Reads the exit code written by the wrapper once the server exited.
+fn read_exit_code(directory: &Path) -> Option<i32> {
+    std::fs::read_to_string(directory.join(CONSOLE_DIRECTORY).join(EXIT_CODE_FILE))
+        .ok()
+        .and_then(|code| code.trim().parse().ok())
+}
+
+/// Returns whether a process with the given pid exists.
+#[cfg(unix)]
+fn process_exists(pid: u64) -> bool {
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    // EPERM means the process exists but belongs to another user.
+    matches!(
+        kill(Pid::from_raw(pid as i32), None),
+        Ok(_) | Err(nix::errno::Errno::EPERM)
+    )
+}
+
+/// Returns whether the process with the given pid is still the server running in `directory`,
+/// and not an unrelated process that was given the same pid after the server exited.
+#[cfg(unix)]
+fn process_belongs_to_server(pid: u64, directory: &Path) -> bool {
+    if !process_exists(pid) {
+        return false;
+    }
+    if cfg!(target_os = "linux") {
+        match (
+            std::fs::read_link(format!("/proc/{}/cwd", pid)),
+            std::fs::canonicalize(directory),
+        ) {
+            (Ok(cwd), Ok(directory)) => cwd == directory,
+            _ => false,
+        }
+    } else {
+        true
+    }
+}
+
+/// Reconciles the status and pid of every server with the processes that are actually running.
+///
+/// The running servers list only lives in memory, so after the panel restarts the servers that
+/// kept running are adopted again, or killed when `kill_orphans` is set. Servers whose process
+/// is gone are marked `Offline` if they exited cleanly or were stopping, and `Crashed` otherwise.
+///
+/// Only unix servers can be adopted, on other platforms the console pipes closed with the panel.
+///
+/// # Arguments
+/// * `kill_orphans` - Kills the servers that are still running instead of adopting them.
+pub fn recover_server_processes(kill_orphans: bool) -> Result<(), Box<dyn Error>> {
+    for mut server in Server::get_list_of_servers()? {
+        let status = server.status.clone().unwrap_or_default();
+        let was_running = matches!(
+            status,
+            ServerStatus::Starting
+                | ServerStatus::Online
+                | ServerStatus::Stopping
+                | ServerStatus::Restarting
+                | ServerStatus::FailedToStart
+        );
+        if !was_running && server.pid.is_none() {
+            continue;
+        }
+
+        #[cfg(unix)]
+        if let Some(pid) = server
+            .pid
+            .filter(|pid| process_belongs_to_server(*pid, &server.directory))
+        {
+            if !kill_orphans {
+                if let Err(e) = server.adopt_process(pid) {
+                    warn!("Failed to adopt server {:?} (pid {}): {}", server.name, pid, e);
+                }
+                continue;
+            }
+
+            info!("Killing orphaned server {:?} (pid {})", server.name, pid);
+            terminate_process(pid, false)?;
+            let deadline = Instant::now() + TERMINATE_TIMEOUT;
+            while process_exists(pid) && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(250));
+            }
+            if process_exists(pid) {
+                terminate_process(pid, true)?;
+            }
+            end_sessions(server.id);
+            update_server_status(server.id, ServerStatus::Offline)?;
+            continue;
+        }
+        #[cfg(not(unix))]
+        if let Some(pid) = server.pid {
+            let _ = kill_orphans;
+            warn!(
+                "Server {:?} can't be recovered on this platform, its process {} may still be running",
+                server.name, pid
+            );
+        }
+
+        let exit_code = read_exit_code(&server.directory);
+        let final_status = if status == ServerStatus::Stopping || exit_code == Some(0) || !was_running {
+            ServerStatus::Offline
+        } else {
+            let console = read_console_log_tail(&server.directory);
+            if let Err(e) = record_crash(server.id, exit_code, &console) {
+                warn!("Failed to record crash of server {:?}: {}", server.name, e);
+            }
+            ServerStatus::Crashed
+        };
+        info!(
+            "Server {:?} is no longer running, marking it as {}",
+            server.name, final_status
+        );
+        end_sessions(server.id);
+        update_server_status(server.id, final_status)?;
+    }
+    Ok(())
+}
+
+/// Reads the last lines of the console logs written while the panel was down.
+///
+/// The order between stdout and stderr isn't known from the files alone, so the
+/// stderr lines are put last, where the reason of a crash usually is.
+fn read_console_log_tail(directory: &Path) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    #[cfg(unix)]
+    for (source, file) in [
+        (ConsoleSource::Stdout, CONSOLE_LOG),
+        (ConsoleSource::Stderr, STDERR_LOG),
+    ] {
+        if let Ok(console) = std::fs::read_to_string(directory.join(CONSOLE_DIRECTORY).join(file)) {
+            lines.extend(console.lines().map(|line| ConsoleLine::new(source, line).to_string()));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = directory;
+    lines.split_off(lines.len().saturating_sub(CRASH_CONSOLE_LINES))
+}
+
+/// Records a crash and applies the server's restart policy after the server exited without the panel asking it to.
+///
+/// If the policy allows it, the server is restarted after an exponential backoff, unless the
+/// allowed number of attempts within the policy window has already been used up.
+///
+/// # Arguments
+/// * `server_id` - The id of the server that exited.
+/// * `exit_code` - The exit code of the process, if any.
+/// * `success` - Whether the process exited successfully.
+/// * `console` - The last console lines printed by the server.
+fn handle_unexpected_exit(server_id: u64, exit_code: Option<i32>, success: bool, console: Vec<String>) {
+    let crashed = !success;
+    if crashed {
+        if let Err(e) = record_crash(server_id, exit_code, &console) {
+            warn!("Failed to record crash of server {}: {}", server_id, e);
+        }
+    }
+
+    let final_status = if crashed {
+        ServerStatus::Crashed
+    } else {
+        ServerStatus::Offline
+    };
+
+    let policy = match Server::get_server(server_id) {
+        Ok(server) => server.restart_policy,
+        Err(e) => {
+            warn!("Failed to load server {} after it exited: {}", server_id, e);
+            return;
+        }
+    };
+
+    if !policy.should_restart(crashed) {
+        if let Err(e) = update_server_status(server_id, final_status) {
+            warn!("Failed to update server status: {}", e);
+        }
+        return;
+    }
+
+    // Only count the attempts made within the policy window.
+    let previous_attempts = match RESTART_ATTEMPTS.lock() {
+        Ok(mut attempts) => {
+            let window = Duration::from_secs(policy.window_seconds);
+            let attempts = attempts.entry(server_id).or_default();
+            attempts.retain(|attempt| attempt.elapsed() < window);
+            if attempts.len() >= policy.max_attempts as usize {
+                None
+            } else {
+                let previous_attempts = attempts.len() as u32;
+                attempts.push(Instant::now());
+                Some(previous_attempts)
+            }
+        }
+        Err(_) => None,
+    };
+
+    let Some(previous_attempts) = previous_attempts else {
+        warn!(
+            "Server {} exited {} times within {} seconds, giving up on restarting it",
+            server_id, policy.max_attempts, policy.window_seconds
+        );
+        if let Err(e) = update_server_status(server_id, final_status) {
+            warn!("Failed to update server status: {}", e);
+        }
+        return;
+    };
+
+    let backoff = policy.backoff(previous_attempts);
+    info!(
+        "Restarting server {} in {:?} (attempt {} of {})",
+        server_id,
+        backoff,
+        previous_attempts + 1,
+        policy.max_attempts
+    );
+    if let Err(e) = update_server_status(server_id, ServerStatus::Restarting) {
+        warn!("Failed to update server status: {}", e);
+    }
+
+    if let Ok(mut pending) = PENDING_RESTARTS.lock() {
+        pending.insert(server_id);
+    }
+    thread::sleep(backoff);
+
+    // The restart was cancelled by a stop, or the server was started manually in the meantime.
+    let still_pending = PENDING_RESTARTS
+        .lock()
+        .map(|mut pending| pending.remove(&server_id))
+        .unwrap_or(false);
+    if !still_pending || is_running(server_id) {
+        return;
+    }
+
+    let result = Server::get_server(server_id).and_then(|mut server| server.spawn_server(ServerStatus::Restarting));
+    if let Err(e) = result {
+        warn!("Failed to automatically restart server {}: {}", server_id, e);
+        if let Err(e) = update_server_status(server_id, final_status) {
+            warn!("Failed to update server status: {}", e);
+        }
+    }
+}
+
+/// Stops every running server in parallel because the panel is shutting down, and refuses to start
+/// servers from then on, e.g. for an automatic restart or a player waking a sleeping server.
+///
+/// `timeout` bounds the whole shutdown: a server that doesn't stop on its own is terminated in time
+/// for the SIGTERM and SIGKILL waits to end by then as well.
+///
+/// # Returns
+/// The number of servers that were stopped.
+pub fn stop_all_servers(timeout: Duration) -> usize {
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+    // Sleeping servers keep their status, so they wait for players again when the panel is back.
+    stop_all_wake_listeners();
+
+    // Servers waiting for an automatic restart have no process to stop.
+    let pending: Vec<u64> = PENDING_RESTARTS
+        .lock()
+        .map(|mut pending| pending.drain().collect())
+        .unwrap_or_default();
+    for server_id in pending {
+        if let Err(e) = update_server_status(server_id, ServerStatus::Offline) {
+            warn!("Failed to update server status: {}", e);
         }
-        Ok(())
     }
+
+    let server_ids: Vec<u64> = RUNNING_SERVERS
+        .lock()
+        .map(|servers| {
+            servers
+                .iter()
+                .filter_map(|s| s.lock().ok().map(|server| server.server_id))
+                .collect()
+        })
+        .unwrap_or_default();
+    let deadline = Instant::now() + timeout;
+    let handles: Vec<_> = server_ids
+        .into_iter()
+        .map(|server_id| {
+            thread::spawn(move || {
+                let mut server = match Server::get_server(server_id) {
+                    Ok(server) => server,
+                    Err(e) => {
+                        warn!("Failed to stop server {}: {}", server_id, e);
+                        return false;
+                    }
+                };
+                let (grace_period, terminate_timeout) =
+                    split_stop_timeout(deadline.saturating_duration_since(Instant::now()));
+                match server.stop_process(grace_period, terminate_timeout, ExitIntent::Stop) {
+                    Ok(pid) => {
+                        info!("Stopped server {:?} (pid {})", server.name, pid);
+                        true
+                    }
+                    Err(e) => {
+                        warn!("Failed to stop server {:?}: {}", server.name, e);
+                        false
+                    }
+                }
+            })
+        })
+        .collect();
+    handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap_or(false))
+        .filter(|stopped| *stopped)
+        .count()
+}
+
+/// Splits the time left to stop a server into the grace period after `stop` and the wait after each signal,
+/// so the SIGTERM and SIGKILL escalation finishes within it too.
+fn split_stop_timeout(remaining: Duration) -> (Duration, Duration) {
+    let terminate_timeout = TERMINATE_TIMEOUT.min(remaining / 4);
+    (remaining.saturating_sub(terminate_timeout * 2), terminate_timeout)
+}
+
+/// Cancels the automatic restart a server is waiting for, if any.
+///
+/// # Returns
+/// `true` if a pending restart was cancelled.
+fn cancel_pending_restart(server_id: u64) -> bool {
+    PENDING_RESTARTS
+        .lock()
+        .map(|mut pending| pending.remove(&server_id))
+        .unwrap_or(false)
+}
+
+/// Settles the outcome of a server start, unless it's already settled or the server isn't starting anymore.
+///
+/// # Returns
+/// Whether the status was updated.
+fn settle_start(server_id: u64, settled: &AtomicBool, status: ServerStatus) -> bool {
+    if settled.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    // The server may have been stopped in the meantime, which decides the status on its own.
+    let starting = Server::get_server(server_id)
+        .map(|server| matches!(server.status, Some(ServerStatus::Starting | ServerStatus::Restarting)))
+        .unwrap_or(false);
+    if !starting {
+        return false;
+    }
+    if let Err(e) = update_server_status(server_id, status) {
+        warn!("Failed to update server status: {}", e);
+        return false;
+    }
+    true
+}
+
+/// Updates only the status of a server in the database, so changes made to the other settings while
+/// the server was running are not overwritten. The pid is synced with the running servers list.
+pub(crate) fn update_server_status(server_id: u64, status: ServerStatus) -> Result<(), Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare("UPDATE server SET status = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")?;
+    statement.bind((1, status.to_string().as_str()))?;
+    statement.bind((2, server_id as i64))?;
+    statement.next()?;
+    update_server_pid(server_id, running_pid(server_id))
+}
+
+/// Updates only the process ID of a server in the database, `None` once the server has no process.
+pub(crate) fn update_server_pid(server_id: u64, pid: Option<u64>) -> Result<(), Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare("UPDATE server SET pid = ? WHERE id = ?")?;
+    statement.bind((1, pid.map(|pid| pid as i64)))?;
+    statement.bind((2, server_id as i64))?;
+    statement.next()?;
+    Ok(())
 }
@@ -0,0 +1,314 @@
+use crate::server::Server;
+use crate::server_database::ServerDatabase;
+use crate::server_process::is_running;
+use crate::server_properties::ServerProperties;
+use crate::start_validation::server_port;
+use serde_derive::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The magic bytes every query request starts with.
+const QUERY_MAGIC: [u8; 2] = [0xFE, 0xFD];
+/// The packet type of a handshake, which returns a challenge token.
+const QUERY_TYPE_HANDSHAKE: u8 = 0x09;
+/// The packet type of a stat request.
+const QUERY_TYPE_STAT: u8 = 0x00;
+/// Minecraft only reads the low 4 bits of every byte of the session id.
+const SESSION_ID_MASK: i32 = 0x0F0F_0F0F;
+/// The padding before the key-value section of a full stat.
+const FULL_STAT_KEYS_PADDING: usize = 11;
+/// The padding between the key-value section and the player list of a full stat.
+const FULL_STAT_PLAYERS_PADDING: usize = 10;
+/// The largest response accepted, a full stat of a server with many players fits in a single datagram.
+const MAX_RESPONSE_SIZE: usize = 65535;
+/// The timeout of every request.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A session with a server over the GameSpy4 query protocol.
+pub struct QueryClient {
+    socket: UdpSocket,
+    session_id: i32,
+    /// The challenge token of the handshake, which the server expires every 30 seconds.
+    challenge: i32,
+}
+
+/// The result of a basic stat.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryBasicStat {
+    pub motd: String,
+    pub game_type: String,
+    pub map: String,
+    pub players_online: u32,
+    pub players_max: u32,
+    pub host_port: u16,
+    pub host_ip: String,
+}
+
+/// The result of a full stat.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryFullStat {
+    pub motd: String,
+    pub game_type: String,
+    pub game_id: String,
+    pub version: String,
+    /// The server software reported in the `plugins` field, e.g. `Paper on Bukkit 1.21.1`.
+    pub server_mod: Option<String>,
+    /// The plugins with their versions, e.g. `EssentialsX 2.20.1`.
+    pub plugins: Vec<String>,
+    pub map: String,
+    pub players_online: u32,
+    pub players_max: u32,
+    pub host_port: u16,
+    pub host_ip: String,
+    /// The names of all online players.
+    pub players: Vec<String>,
+}
+
+impl QueryClient {
+    /// Opens a session with a server and performs the handshake.
+    ///
+    /// # Arguments
+    /// * `address` - The address of the server's query port.
+    pub fn connect(address: SocketAddr) -> Result<Self, Box<dyn Error>> {
+        let socket = UdpSocket::bind(if address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })?;
+        socket.connect(address)?;
+        socket.set_read_timeout(Some(QUERY_TIMEOUT))?;
+        socket.set_write_timeout(Some(QUERY_TIMEOUT))?;
+        let session_id = SystemTime::now().duration_since(UNIX_EPOCH)?.subsec_nanos() as i32 & SESSION_ID_MASK;
+        let mut client = Self {
+            socket,
+            session_id,
+            challenge: 0,
+        };
+        client.handshake()?;
+        Ok(client)
+    }
+
+    /// Requests a new challenge token.
+    pub fn handshake(&mut self) -> Result<(), Box<dyn Error>> {
+        let response = self.request(QUERY_TYPE_HANDSHAKE, &[])?;
+        // The token is sent as a null-terminated decimal string.
+        let mut reader = Reader::new(&response);
+        self.challenge = reader.string()?.trim().parse()?;
+        Ok(())
+    }
+
+    /// Requests the basic stat.
+    pub fn basic_stat(&mut self) -> Result<QueryBasicStat, Box<dyn Error>> {
+        let response = self.request(QUERY_TYPE_STAT, &self.challenge.to_be_bytes())?;
+        let mut reader = Reader::new(&response);
+        Ok(QueryBasicStat {
+            motd: reader.string()?,
+            game_type: reader.string()?,
+            map: reader.string()?,
+            players_online: reader.string()?.parse()?,
+            players_max: reader.string()?.parse()?,
+            host_port: reader.u16_le()?,
+            host_ip: reader.string()?,
+        })
+    }
+
+    /// Requests the full stat, which includes the plugins and every online player.
+    pub fn full_stat(&mut self) -> Result<QueryFullStat, Box<dyn Error>> {
+        let mut payload = self.challenge.to_be_bytes().to_vec();
+        // The padding is what tells the server to send the full stat.
+        payload.extend_from_slice(&[0; 4]);
+        let response = self.request(QUERY_TYPE_STAT, &payload)?;
+
+        let mut reader = Reader::new(&response);
+        reader.skip(FULL_STAT_KEYS_PADDING)?;
+        let mut values = HashMap::new();
+        loop {
+            let key = reader.string()?;
+            if key.is_empty() {
+                break;
+            }
+            values.insert(key, reader.string()?);
+        }
+        reader.skip(FULL_STAT_PLAYERS_PADDING)?;
+        let mut players = Vec::new();
+        loop {
+            let player = reader.string()?;
+            if player.is_empty() {
+                break;
+            }
+            players.push(player);
+        }
+
+        let mut value = |key: &str| values.remove(key).unwrap_or_default();
+        let (server_mod, plugins) = parse_plugins(&value("plugins"));
+        Ok(QueryFullStat {
+            motd: value("hostname"),
+            game_type: value("gametype"),
+            game_id: value("game_id"),
+            version: value("version"),
+            server_mod,
+            plugins,
+            map: value("map"),
+            players_online: value("numplayers").parse().unwrap_or_default(),
+            players_max: value("maxplayers").parse().unwrap_or_default(),
+            host_port: value("hostport").parse().unwrap_or_default(),
+            host_ip: value("hostip"),
+            players,
+        })
+    }
+
+    /// Sends a request and returns the response after its type and session id.
+    fn request(&mut self, kind: u8, payload: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut packet = QUERY_MAGIC.to_vec();
+        packet.push(kind);
+        packet.extend_from_slice(&self.session_id.to_be_bytes());
+        packet.extend_from_slice(payload);
+        self.socket.send(&packet)?;
+
+        let mut response = vec![0u8; MAX_RESPONSE_SIZE];
+        let size = self.socket.recv(&mut response)?;
+        if size < 5 || response[0] != kind || response[1..5] != self.session_id.to_be_bytes() {
+            return Err("Unexpected query response".into());
+        }
+        response.truncate(size);
+        Ok(response.split_off(5))
+    }
+}
+
+/// Splits the `plugins` field, `<server mod>: <plugin>; <plugin>`, which vanilla servers leave empty.
+fn parse_plugins(value: &str) -> (Option<String>, Vec<String>) {
+    if value.is_empty() {
+        return (None, Vec::new());
+    }
+    let Some((server_mod, plugins)) = value.split_once(':') else {
+        return (Some(value.trim().to_string()), Vec::new());
+    };
+    let plugins = plugins
+        .split(';')
+        .map(str::trim)
+        .filter(|plugin| !plugin.is_empty())
+        .map(str::to_string)
+        .collect();
+    (Some(server_mod.trim().to_string()), plugins)
+}
+
+/// Reads the null-terminated strings and numbers of a query response.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), Box<dyn Error>> {
+        self.data = self.data.get(count..).ok_or("Query response is too short")?;
+        Ok(())
+    }
+
+    /// Reads a null-terminated string, which Minecraft encodes as ISO-8859-1.
+    fn string(&mut self) -> Result<String, Box<dyn Error>> {
+        let end = self
+            .data
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or("Unterminated string in query response")?;
+        let value = self.data[..end].iter().map(|byte| *byte as char).collect();
+        self.data = &self.data[end + 1..];
+        Ok(value)
+    }
+
+    fn u16_le(&mut self) -> Result<u16, Box<dyn Error>> {
+        let bytes = self.data.get(..2).ok_or("Query response is too short")?;
+        let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+        self.data = &self.data[2..];
+        Ok(value)
+    }
+}
+
+/// The query settings of a server, read from its `server.properties`.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuerySettings {
+    /// Whether `enable-query` is set to `true`.
+    pub enabled: bool,
+    /// The `query.port`, which Minecraft defaults to the `server-port`.
+    pub port: u16,
+}
+
+pub trait ServerQuery {
+    /// Reads the query settings from `server.properties`.
+    fn query_settings(&self) -> Result<QuerySettings, Box<dyn Error>>;
+
+    /// Turns the query protocol on or off, which takes effect the next time the server starts.
+    ///
+    /// Enabling it picks a port that no other server queries on if the current one is taken.
+    fn set_query_enabled(&self, enabled: bool) -> Result<QuerySettings, Box<dyn Error>>;
+
+    /// Requests the full stat of the server.
+    fn query_server(&self) -> Result<QueryFullStat, Box<dyn Error>>;
+
+    /// Requests the basic stat of the server.
+    fn query_server_basic(&self) -> Result<QueryBasicStat, Box<dyn Error>>;
+}
+
+impl ServerQuery for Server<u64> {
+    fn query_settings(&self) -> Result<QuerySettings, Box<dyn Error>> {
+        Ok(query_settings_from(&self.get_properties()?, server_port(self)))
+    }
+
+    fn set_query_enabled(&self, enabled: bool) -> Result<QuerySettings, Box<dyn Error>> {
+        let settings = self.query_settings()?;
+        let mut values = HashMap::from([("enable-query".to_string(), enabled.to_string())]);
+        // A running server holds its own query port, which would look taken.
+        if enabled && !(settings.enabled && is_running(self.id)) {
+            let port = find_query_port(self.id, settings.port)?;
+            values.insert("query.port".to_string(), port.to_string());
+        }
+        self.set_property_range(values)?;
+        self.query_settings()
+    }
+
+    fn query_server(&self) -> Result<QueryFullStat, Box<dyn Error>> {
+        connect_query(self)?.full_stat()
+    }
+
+    fn query_server_basic(&self) -> Result<QueryBasicStat, Box<dyn Error>> {
+        connect_query(self)?.basic_stat()
+    }
+}
+
+/// Opens a query session with a server on localhost.
+fn connect_query(server: &Server<u64>) -> Result<QueryClient, Box<dyn Error>> {
+    let settings = server.query_settings()?;
+    if !settings.enabled {
+        return Err("Query isn't enabled for this server".into());
+    }
+    QueryClient::connect(SocketAddr::from(([127, 0, 0, 1], settings.port)))
+}
+
+/// Reads the query settings from the properties of a server.
+fn query_settings_from(properties: &HashMap<String, String>, server_port: u16) -> QuerySettings {
+    QuerySettings {
+        enabled: properties.get("enable-query").is_some_and(|enabled| enabled == "true"),
+        port: properties
+            .get("query.port")
+            .and_then(|port| port.trim().parse().ok())
+            .unwrap_or(server_port),
+    }
+}
+
+/// Finds a UDP port for the query, keeping `preferred` if no other server uses it and it's free.
+fn find_query_port(server_id: u64, preferred: u16) -> Result<u16, Box<dyn Error>> {
+    let mut taken: Vec<u16> = Vec::new();
+    for other in Server::get_list_of_servers()? {
+        if other.id == server_id {
+            continue;
+        }
+        let properties = other.get_properties().unwrap_or_default();
+        taken.push(query_settings_from(&properties, server_port(&other)).port);
+    }
+
+    (preferred..=u16::MAX)
+        .chain(1024..preferred)
+        .find(|port| !taken.contains(port) && UdpSocket::bind(("0.0.0.0", *port)).is_ok())
+        .ok_or_else(|| "No free port for the query".into())
+}
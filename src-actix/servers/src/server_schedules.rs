@@ -0,0 +1,829 @@
+use crate::server::Server;
+use crate::server_database::{add_missing_columns, ServerDatabase};
+use crate::server_players::online_players;
+use crate::server_process::{is_running, ServerProcess};
+use crate::server_status::ServerStatus;
+use chrono::{Local, NaiveTime, TimeDelta};
+use database::{create_appdb_connection, last_inserted_id};
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use regex::Regex;
+use scheduler::duration::Duration;
+use scheduler::schedule_manager::SCHEDULE_MANAGER_SINGLETON;
+use serde_derive::{Deserialize, Serialize};
+use sqlite::State;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The shortest interval a schedule can run at, in seconds.
+pub const MIN_SCHEDULE_INTERVAL_SECONDS: u64 = 10;
+/// The warnings a restart broadcasts by default, in seconds before the restart.
+pub const DEFAULT_RESTART_COUNTDOWN: [u64; 4] = [600, 300, 60, 10];
+/// The command that broadcasts a countdown warning by default, `{time}` is replaced with e.g. `5 minutes`.
+pub const DEFAULT_COUNTDOWN_COMMAND: &str = "say The server restarts in {time}";
+/// The number of task runs kept per server, older runs are deleted when new ones are recorded.
+pub const TASK_HISTORY_LIMIT: u64 = 500;
+/// Broadcast when a restart fails after the countdown while the server keeps running, so the players
+/// aren't left waiting for it.
+const RESTART_FAILED_COMMAND: &str = "say The restart failed, the server keeps running";
+/// How long a restart waits for `save-all` to finish before restarting anyway.
+const SAVE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+lazy_static! {
+    /// The id each schedule got in `SCHEDULE_MANAGER_SINGLETON`, by the id of its database record.
+    static ref REGISTERED_SCHEDULES: Mutex<HashMap<u64, u64>> = Mutex::new(HashMap::new());
+    /// The schedules that are currently running, so a long restart countdown doesn't overlap itself.
+    static ref RUNNING_SCHEDULES: Mutex<HashSet<u64>> = Mutex::new(HashSet::new());
+}
+
+/// What a schedule does when it runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleKind {
+    /// Sends the schedule's console command.
+    #[default]
+    Command,
+    /// Warns the players with the countdown, saves the world and restarts the server.
+    Restart,
+}
+
+/// A task that runs on a server at a fixed interval, either a console command or a restart.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerSchedule {
+    /// The unique identifier of the schedule.
+    pub id: u64,
+    /// The name shown for the schedule, e.g. `Restart announcement`.
+    pub name: String,
+    /// What the schedule does when it runs.
+    pub kind: ScheduleKind,
+    /// The console command, e.g. `say Vote for us!` or `save-all`, empty for restarts.
+    pub command: String,
+    /// How often the schedule runs, in seconds.
+    pub interval_seconds: u64,
+    /// The local time of day the schedule first runs at, formatted as `HH:MM`, e.g. `04:00` for
+    /// a nightly restart. Without one, it first runs an interval after it's registered.
+    pub run_at: Option<String>,
+    /// The warnings a restart broadcasts, in seconds before the restart. The countdown starts
+    /// when the schedule runs, so the restart itself happens after the longest warning.
+    pub countdown: Vec<u64>,
+    /// The command that broadcasts a countdown warning, `{time}` is replaced with e.g. `5 minutes`.
+    pub countdown_command: String,
+    /// Whether the schedule runs, disabled schedules are kept but not registered.
+    pub enabled: bool,
+    /// Whether the schedule is skipped while the server isn't online, instead of failing.
+    /// A restart that isn't skipped starts the server.
+    pub skip_when_offline: bool,
+    /// Whether the schedule is skipped while no players are online.
+    pub skip_when_empty: bool,
+    /// When the schedule last ran, in milliseconds since the Unix epoch.
+    pub last_run_at: Option<u64>,
+    /// The time the schedule was created, formatted as `YYYY-MM-DD HH:MM:SS` in UTC.
+    pub created_at: String,
+}
+
+/// The fields of a schedule that can be set when creating or updating it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduleRequest {
+    pub name: String,
+    #[serde(default)]
+    pub kind: ScheduleKind,
+    #[serde(default)]
+    pub command: String,
+    pub interval_seconds: u64,
+    #[serde(default)]
+    pub run_at: Option<String>,
+    #[serde(default = "default_countdown")]
+    pub countdown: Vec<u64>,
+    #[serde(default = "default_countdown_command")]
+    pub countdown_command: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_true")]
+    pub skip_when_offline: bool,
+    #[serde(default)]
+    pub skip_when_empty: bool,
+}
+
+/// The result of a task run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskResult {
+    Succeeded,
+    Failed,
+    /// The server was offline or empty and the schedule skips those, or the previous run was
+    /// still going.
+    Skipped,
+}
+
+/// A run of a schedule, recorded in the task history.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskRun {
+    /// The unique identifier of the run.
+    pub id: u64,
+    /// The id of the schedule, which may have been deleted since.
+    pub schedule: u64,
+    /// The name of the schedule at the time of the run.
+    pub name: String,
+    pub kind: ScheduleKind,
+    pub result: TaskResult,
+    /// What happened, e.g. the new pid of a restart or the reason it failed.
+    pub message: String,
+    /// When the run started, in milliseconds since the Unix epoch.
+    pub started_at: u64,
+    /// When the run finished, in milliseconds since the Unix epoch.
+    pub finished_at: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_countdown() -> Vec<u64> {
+    DEFAULT_RESTART_COUNTDOWN.to_vec()
+}
+
+fn default_countdown_command() -> String {
+    DEFAULT_COUNTDOWN_COMMAND.to_string()
+}
+
+impl ScheduleRequest {
+    /// Checks that the schedule has what its kind needs and doesn't run more often than allowed.
+    ///
+    /// # Returns
+    /// A description of the problem if the request is invalid.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.kind == ScheduleKind::Command && self.command.trim().is_empty() {
+            return Err("The command can't be empty".to_string());
+        }
+        if self.interval_seconds < MIN_SCHEDULE_INTERVAL_SECONDS {
+            return Err(format!(
+                "The interval has to be at least {} seconds",
+                MIN_SCHEDULE_INTERVAL_SECONDS
+            ));
+        }
+        if let Some(run_at) = &self.run_at {
+            if NaiveTime::parse_from_str(run_at, "%H:%M").is_err() {
+                return Err(format!("Invalid time of day {:?}, expected HH:MM", run_at));
+            }
+        }
+        if self.kind == ScheduleKind::Restart {
+            if self.countdown.contains(&0) {
+                return Err("The countdown warnings have to be at least 1 second".to_string());
+            }
+            if self.countdown.iter().any(|warning| *warning >= self.interval_seconds) {
+                return Err("The countdown has to be shorter than the interval".to_string());
+            }
+            if !self.countdown.is_empty() && self.countdown_command.trim().is_empty() {
+                return Err("The countdown command can't be empty".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Creates the `server_schedule` and `server_task_run` tables if they don't already exist.
+///
+/// # Arguments
+///
+/// * `conn` - The connection to the application database.
+pub(crate) fn initialize_schedule_database(conn: &sqlite::Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS `server_schedule` (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,                       -- Unique identifier for each schedule
+            server INTEGER NOT NULL,                                    -- ID of the server the command is sent to
+            name TEXT NOT NULL,                                         -- Name shown for the schedule
+            command TEXT NOT NULL,                                      -- Console command to send
+            interval_seconds INTEGER NOT NULL,                          -- How often the command is sent, in seconds
+            enabled BOOLEAN NOT NULL DEFAULT 1,                         -- Whether the schedule runs
+            skip_when_offline BOOLEAN NOT NULL DEFAULT 1,               -- Whether to skip the command while the server isn't online
+            skip_when_empty BOOLEAN NOT NULL DEFAULT 0,                 -- Whether to skip the command while no players are online
+            last_run_at INTEGER NULL DEFAULT NULL,                      -- When the command was last sent, in milliseconds since the Unix epoch
+            created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP     -- Timestamp of creation
+        );
+
+        CREATE TABLE IF NOT EXISTS `server_task_run` (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,                       -- Unique identifier for each run
+            server INTEGER NOT NULL,                                    -- ID of the server the task ran on
+            schedule INTEGER NOT NULL,                                  -- ID of the schedule, kept after it's deleted
+            name TEXT NOT NULL,                                         -- Name of the schedule at the time of the run
+            kind TEXT NOT NULL,                                         -- What the schedule did, `command` or `restart`
+            result TEXT NOT NULL,                                       -- `succeeded`, `failed` or `skipped`
+            message TEXT NOT NULL,                                      -- What happened
+            started_at INTEGER NOT NULL,                                -- When the run started, in milliseconds since the Unix epoch
+            finished_at INTEGER NOT NULL                                -- When the run finished, in milliseconds since the Unix epoch
+        );
+"#,
+    )?;
+
+    // Columns added after the initial schema, along with their definitions
+    let columns = [
+        ("kind", "TEXT NOT NULL DEFAULT 'command'"),
+        ("run_at", "TEXT NULL DEFAULT NULL"),
+        ("countdown", "TEXT NULL DEFAULT NULL"),
+        ("countdown_command", "TEXT NULL DEFAULT NULL"),
+    ];
+    add_missing_columns(conn, "server_schedule", &columns)?;
+    Ok(())
+}
+
+/// Registers every enabled schedule in `SCHEDULE_MANAGER_SINGLETON`, called once when the panel starts.
+///
+/// # Returns
+/// The number of schedules registered.
+pub fn load_schedules() -> Result<usize, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare("SELECT * FROM server_schedule WHERE enabled = 1")?;
+    let mut schedules = Vec::new();
+    while let State::Row = statement.next()? {
+        schedules.push(read_schedule(&statement)?);
+    }
+
+    for schedule in &schedules {
+        register_schedule(schedule)?;
+    }
+    info!("Loaded {} server schedules", schedules.len());
+    Ok(schedules.len())
+}
+
+/// Retrieves the schedules of a server, oldest first.
+///
+/// # Arguments
+///
+/// * `server_id` - The ID of the server.
+pub fn get_schedules(server_id: u64) -> Result<Vec<ServerSchedule>, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare("SELECT * FROM server_schedule WHERE server = ? ORDER BY id")?;
+    statement.bind((1, server_id as i64))?;
+
+    let mut schedules = Vec::new();
+    while let State::Row = statement.next()? {
+        schedules.push(read_schedule(&statement)?);
+    }
+    Ok(schedules)
+}
+
+/// Retrieves a schedule of a server.
+///
+/// # Returns
+/// `None` if the server has no schedule with the id.
+pub fn get_schedule(server_id: u64, schedule_id: u64) -> Result<Option<ServerSchedule>, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare("SELECT * FROM server_schedule WHERE id = ? AND server = ?")?;
+    statement.bind((1, schedule_id as i64))?;
+    statement.bind((2, server_id as i64))?;
+    match statement.next()? {
+        State::Row => Ok(Some(read_schedule(&statement)?)),
+        State::Done => Ok(None),
+    }
+}
+
+/// Creates a schedule for a server and registers it if it's enabled.
+pub fn create_schedule(server_id: u64, request: &ScheduleRequest) -> Result<ServerSchedule, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare(
+        "INSERT INTO server_schedule (server, name, command, interval_seconds, enabled, skip_when_offline, skip_when_empty, kind, run_at, countdown, countdown_command) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )?;
+    statement.bind((1, server_id as i64))?;
+    bind_request(&mut statement, 2, request)?;
+    statement.next()?;
+
+    let id = last_inserted_id("server_schedule")?;
+    let schedule = get_schedule(server_id, id)?.ok_or("Failed to create the schedule")?;
+    if schedule.enabled {
+        register_schedule(&schedule)?;
+    }
+    Ok(schedule)
+}
+
+/// Updates a schedule of a server and registers it again with the new settings.
+///
+/// # Returns
+/// `None` if the server has no schedule with the id.
+pub fn update_schedule(
+    server_id: u64,
+    schedule_id: u64,
+    request: &ScheduleRequest,
+) -> Result<Option<ServerSchedule>, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare(
+        "UPDATE server_schedule SET name = ?, command = ?, interval_seconds = ?, enabled = ?, skip_when_offline = ?, skip_when_empty = ?, kind = ?, run_at = ?, countdown = ?, countdown_command = ? WHERE id = ? AND server = ?",
+    )?;
+    bind_request(&mut statement, 1, request)?;
+    statement.bind((11, schedule_id as i64))?;
+    statement.bind((12, server_id as i64))?;
+    statement.next()?;
+
+    let Some(schedule) = get_schedule(server_id, schedule_id)? else {
+        return Ok(None);
+    };
+    unregister_schedule(schedule.id)?;
+    if schedule.enabled {
+        register_schedule(&schedule)?;
+    }
+    Ok(Some(schedule))
+}
+
+/// Deletes a schedule of a server.
+///
+/// # Returns
+/// `false` if the server has no schedule with the id.
+pub fn delete_schedule(server_id: u64, schedule_id: u64) -> Result<bool, Box<dyn Error>> {
+    if get_schedule(server_id, schedule_id)?.is_none() {
+        return Ok(false);
+    }
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare("DELETE FROM server_schedule WHERE id = ? AND server = ?")?;
+    statement.bind((1, schedule_id as i64))?;
+    statement.bind((2, server_id as i64))?;
+    statement.next()?;
+    unregister_schedule(schedule_id)?;
+    Ok(true)
+}
+
+/// Runs a schedule of a server right away, in the background, without changing when it runs next.
+///
+/// # Returns
+/// `false` if the server has no schedule with the id.
+pub fn run_schedule_now(server_id: u64, schedule_id: u64) -> Result<bool, Box<dyn Error>> {
+    if get_schedule(server_id, schedule_id)?.is_none() {
+        return Ok(false);
+    }
+    start_run(schedule_id);
+    Ok(true)
+}
+
+/// Retrieves the task history of a server, newest first.
+///
+/// # Arguments
+///
+/// * `server_id` - The ID of the server.
+/// * `schedule_id` - Only the runs of this schedule, if given.
+/// * `limit` - The maximum number of runs to return.
+pub fn get_task_history(server_id: u64, schedule_id: Option<u64>, limit: u64) -> Result<Vec<TaskRun>, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare(
+        "SELECT * FROM server_task_run WHERE server = ? AND (? IS NULL OR schedule = ?) ORDER BY id DESC LIMIT ?",
+    )?;
+    statement.bind((1, server_id as i64))?;
+    statement.bind((2, schedule_id.map(|id| id as i64)))?;
+    statement.bind((3, schedule_id.map(|id| id as i64)))?;
+    statement.bind((4, limit as i64))?;
+
+    let mut runs = Vec::new();
+    while let State::Row = statement.next()? {
+        runs.push(TaskRun {
+            id: statement.read::<i64, _>("id")? as u64,
+            schedule: statement.read::<i64, _>("schedule")? as u64,
+            name: statement.read::<String, _>("name")?,
+            kind: statement.read::<String, _>("kind")?.parse()?,
+            result: statement.read::<String, _>("result")?.parse()?,
+            message: statement.read::<String, _>("message")?,
+            started_at: statement.read::<i64, _>("started_at")? as u64,
+            finished_at: statement.read::<i64, _>("finished_at")? as u64,
+        });
+    }
+    Ok(runs)
+}
+
+/// Deletes every schedule and the task history of a server, called when the server is removed.
+pub(crate) fn delete_server_schedules(conn: &sqlite::Connection, server_id: u64) -> Result<(), Box<dyn Error>> {
+    for schedule in get_schedules(server_id)? {
+        unregister_schedule(schedule.id)?;
+    }
+    let mut statement = conn.prepare("DELETE FROM server_schedule WHERE server = ?")?;
+    statement.bind((1, server_id as i64))?;
+    statement.next()?;
+    let mut statement = conn.prepare("DELETE FROM server_task_run WHERE server = ?")?;
+    statement.bind((1, server_id as i64))?;
+    statement.next()?;
+    Ok(())
+}
+
+/// Binds the fields of a request to ten consecutive placeholders, starting at `first`.
+fn bind_request(
+    statement: &mut sqlite::Statement,
+    first: usize,
+    request: &ScheduleRequest,
+) -> Result<(), Box<dyn Error>> {
+    statement.bind((first, request.name.as_str()))?;
+    statement.bind((first + 1, request.command.trim()))?;
+    statement.bind((first + 2, request.interval_seconds as i64))?;
+    statement.bind((first + 3, request.enabled as i64))?;
+    statement.bind((first + 4, request.skip_when_offline as i64))?;
+    statement.bind((first + 5, request.skip_when_empty as i64))?;
+    statement.bind((first + 6, request.kind.as_str()))?;
+    statement.bind((first + 7, request.run_at.as_deref()))?;
+    statement.bind((first + 8, serde_json::to_string(&request.countdown)?.as_str()))?;
+    statement.bind((first + 9, request.countdown_command.as_str()))?;
+    Ok(())
+}
+
+fn read_schedule(statement: &sqlite::Statement) -> Result<ServerSchedule, Box<dyn Error>> {
+    Ok(ServerSchedule {
+        id: statement.read::<i64, _>("id")? as u64,
+        name: statement.read::<String, _>("name")?,
+        kind: statement.read::<String, _>("kind")?.parse()?,
+        command: statement.read::<String, _>("command")?,
+        interval_seconds: statement.read::<i64, _>("interval_seconds")? as u64,
+        run_at: statement.read::<Option<String>, _>("run_at")?,
+        countdown: match statement.read::<Option<String>, _>("countdown")? {
+            Some(countdown) => serde_json::from_str(&countdown)?,
+            None => default_countdown(),
+        },
+        countdown_command: statement
+            .read::<Option<String>, _>("countdown_command")?
+            .unwrap_or_else(default_countdown_command),
+        enabled: statement.read::<i64, _>("enabled")? != 0,
+        skip_when_offline: statement.read::<i64, _>("skip_when_offline")? != 0,
+        skip_when_empty: statement.read::<i64, _>("skip_when_empty")? != 0,
+        last_run_at: statement.read::<Option<i64>, _>("last_run_at")?.map(|at| at as u64),
+        created_at: statement.read::<String, _>("created_at")?,
+    })
+}
+
+/// Adds a schedule to `SCHEDULE_MANAGER_SINGLETON`, replacing it if it's already registered.
+fn register_schedule(schedule: &ServerSchedule) -> Result<(), Box<dyn Error>> {
+    unregister_schedule(schedule.id)?;
+    let schedule_id = schedule.id;
+    let interval = Duration::from_seconds(schedule.interval_seconds);
+    let action = move |_: &scheduler::schedule::Schedule| start_run(schedule_id);
+    let mut manager = SCHEDULE_MANAGER_SINGLETON
+        .lock()
+        .map_err(|_| "Failed to lock the schedule manager")?;
+    let manager_id = match schedule.run_at.as_deref() {
+        Some(run_at) => manager.add_schedule_starting_at(next_time_of_day(run_at)?, interval, true, action),
+        None => manager.add_schedule(interval, true, false, action),
+    };
+    drop(manager);
+    REGISTERED_SCHEDULES
+        .lock()
+        .map_err(|_| "Failed to lock the registered schedules")?
+        .insert(schedule_id, manager_id);
+    Ok(())
+}
+
+/// Removes a schedule from `SCHEDULE_MANAGER_SINGLETON`, if it's registered.
+fn unregister_schedule(schedule_id: u64) -> Result<(), Box<dyn Error>> {
+    let manager_id = REGISTERED_SCHEDULES
+        .lock()
+        .map_err(|_| "Failed to lock the registered schedules")?
+        .remove(&schedule_id);
+    if let Some(manager_id) = manager_id {
+        SCHEDULE_MANAGER_SINGLETON
+            .lock()
+            .map_err(|_| "Failed to lock the schedule manager")?
+            .remove_schedule(|schedule| schedule.id == manager_id);
+    }
+    Ok(())
+}
+
+/// The next time the local clock shows a time of day, formatted as `HH:MM`.
+fn next_time_of_day(time: &str) -> Result<SystemTime, Box<dyn Error>> {
+    let time = NaiveTime::parse_from_str(time, "%H:%M")?;
+    let now = Local::now();
+    let mut next = now.date_naive().and_time(time);
+    if next <= now.naive_local() {
+        next += TimeDelta::days(1);
+    }
+    // A time skipped by a daylight saving change runs an hour later.
+    let next = next
+        .and_local_timezone(Local)
+        .earliest()
+        .or_else(|| (next + TimeDelta::hours(1)).and_local_timezone(Local).earliest())
+        .ok_or("Invalid local time")?;
+    Ok(next.into())
+}
+
+/// Runs a schedule on its own thread, since the manager is locked while it ticks.
+///
+/// A schedule runs at most once at a time, a run that comes up while the previous one is still going,
+/// e.g. a long restart countdown, is recorded as skipped without starting a thread.
+fn start_run(schedule_id: u64) {
+    let newly_running = RUNNING_SCHEDULES
+        .lock()
+        .map(|mut running| running.insert(schedule_id))
+        .unwrap_or(false);
+    if !newly_running {
+        if let Err(e) = skip_run(schedule_id, "The previous run hasn't finished") {
+            warn!("Failed to skip schedule {}: {}", schedule_id, e);
+        }
+        return;
+    }
+    thread::spawn(move || {
+        if let Err(e) = run_schedule(schedule_id) {
+            warn!("Failed to run schedule {}: {}", schedule_id, e);
+        }
+        if let Ok(mut running) = RUNNING_SCHEDULES.lock() {
+            running.remove(&schedule_id);
+        }
+    });
+}
+
+/// Runs a schedule and records the result in the task history.
+fn run_schedule(schedule_id: u64) -> Result<(), Box<dyn Error>> {
+    // The connection isn't kept open during the run, a restart can take several minutes.
+    let Some((server_id, schedule)) = load_schedule(schedule_id)? else {
+        return Ok(());
+    };
+    let started_at = now_millis()?;
+    let (result, message) =
+        execute_schedule(server_id, &schedule).unwrap_or_else(|e| (TaskResult::Failed, e.to_string()));
+    finish_run(server_id, &schedule, result, &message, started_at)
+}
+
+/// Records a run of a schedule as skipped without running it.
+fn skip_run(schedule_id: u64, reason: &str) -> Result<(), Box<dyn Error>> {
+    let Some((server_id, schedule)) = load_schedule(schedule_id)? else {
+        return Ok(());
+    };
+    finish_run(server_id, &schedule, TaskResult::Skipped, reason, now_millis()?)
+}
+
+/// Reads a schedule with the id of its server, `None` if it was deleted.
+fn load_schedule(schedule_id: u64) -> Result<Option<(u64, ServerSchedule)>, Box<dyn Error>> {
+    let conn = create_appdb_connection()?;
+    let mut statement = conn.prepare("SELECT * FROM server_schedule WHERE id = ?")?;
+    statement.bind((1, schedule_id as i64))?;
+    if statement.next()? != State::Row {
+        return Ok(None);
+    }
+    Ok(Some((
+        statement.read::<i64, _>("server")? as u64,
+        read_schedule(&statement)?,
+    )))
+}
+
+/// Logs the result of a run and records it in the task history, a run that wasn't skipped also becomes
+/// the last run of the schedule.
+fn finish_run(
+    server_id: u64,
+    schedule: &ServerSchedule,
+    result: TaskResult,
+    message: &str,
+    started_at: u64,
+) -> Result<(), Box<dyn Error>> {
+    match result {
+        TaskResult::Succeeded => debug!("Ran schedule {:?}: {}", schedule.name, message),
+        TaskResult::Failed => warn!("Schedule {:?} failed: {}", schedule.name, message),
+        TaskResult::Skipped => debug!("Skipped schedule {:?}: {}", schedule.name, message),
+    }
+    let conn = create_appdb_connection()?;
+    if result != TaskResult::Skipped {
+        let mut statement = conn.prepare("UPDATE server_schedule SET last_run_at = ? WHERE id = ?")?;
+        statement.bind((1, started_at as i64))?;
+        statement.bind((2, schedule.id as i64))?;
+        statement.next()?;
+    }
+    record_task_run(&conn, server_id, schedule, result, message, started_at)
+}
+
+/// Sends the command of a schedule or restarts its server, unless the server is offline or empty
+/// and the schedule skips those.
+///
+/// # Returns
+/// The result of the run along with what happened.
+fn execute_schedule(server_id: u64, schedule: &ServerSchedule) -> Result<(TaskResult, String), Box<dyn Error>> {
+    let mut server = Server::get_server(server_id)?;
+    if server.status != Some(ServerStatus::Online) && schedule.skip_when_offline {
+        return Ok((TaskResult::Skipped, "The server is offline".to_string()));
+    }
+    if schedule.skip_when_empty && online_players(server_id).is_empty() {
+        return Ok((TaskResult::Skipped, "No players are online".to_string()));
+    }
+
+    match schedule.kind {
+        ScheduleKind::Command => {
+            server.send_command_to_server(&schedule.command)?;
+            Ok((TaskResult::Succeeded, format!("Sent {:?}", schedule.command)))
+        }
+        ScheduleKind::Restart => Ok((TaskResult::Succeeded, run_restart(&mut server, schedule)?)),
+    }
+}
+
+/// Broadcasts the countdown, saves the world and restarts the server.
+///
+/// The countdown is left out if no players are online, since there's nobody to warn.
+///
+/// # Returns
+/// What happened, including the pid of the new process.
+fn run_restart(server: &mut Server<u64>, schedule: &ServerSchedule) -> Result<String, Box<dyn Error>> {
+    let mut saved = false;
+    let mut warned = false;
+    if is_running(server.id) {
+        if !online_players(server.id).is_empty() {
+            for (remaining, wait) in countdown_warnings(&schedule.countdown) {
+                if !is_running(server.id) {
+                    return Err("The server stopped during the countdown".into());
+                }
+                let command = schedule
+                    .countdown_command
+                    .replace("{time}", &format_countdown(remaining));
+                server.send_command_to_server(command)?;
+                warned = true;
+                thread::sleep(std::time::Duration::from_secs(wait));
+            }
+        }
+        // Minecraft prints this once `save-all` has written the world.
+        let saved_pattern = Regex::new(r"Saved the (game|world)")?;
+        saved = server
+            .send_command_and_capture("save-all", SAVE_TIMEOUT, Some(&saved_pattern))?
+            .matched;
+        if !saved {
+            warn!(
+                "{:?} didn't confirm saving within {:?}, restarting anyway",
+                server.name, SAVE_TIMEOUT
+            );
+        }
+    }
+
+    let pid = match server.restart_server() {
+        Ok(pid) => pid,
+        // A restart that fails before stopping the server, e.g. on validation, leaves it running.
+        Err(e) if warned && is_running(server.id) => {
+            if let Err(notice_error) = server.send_command_to_server(RESTART_FAILED_COMMAND) {
+                warn!(
+                    "Failed to tell the players on {:?} that the restart failed: {}",
+                    server.name, notice_error
+                );
+            }
+            return Err(e);
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(match saved {
+        true => format!("Saved the world and restarted with pid {}", pid),
+        false => format!("Restarted with pid {} without a confirmed save", pid),
+    })
+}
+
+/// Orders the warnings of a countdown, longest first and without duplicates.
+///
+/// # Returns
+/// The seconds left at each warning, with the seconds to wait after it for the next warning or the restart.
+fn countdown_warnings(countdown: &[u64]) -> Vec<(u64, u64)> {
+    let mut countdown = countdown.to_vec();
+    countdown.sort_unstable_by(|a, b| b.cmp(a));
+    countdown.dedup();
+    countdown
+        .iter()
+        .enumerate()
+        .map(|(i, remaining)| (*remaining, remaining - countdown.get(i + 1).copied().unwrap_or(0)))
+        .collect()
+}
+
+/// Formats the time left in a countdown, e.g. `10 minutes`, `1 hour` or `90 seconds`.
+fn format_countdown(seconds: u64) -> String {
+    let (value, unit) = match seconds {
+        s if s >= 3600 && s % 3600 == 0 => (s / 3600, "hour"),
+        s if s >= 60 && s % 60 == 0 => (s / 60, "minute"),
+        s => (s, "second"),
+    };
+    format!("{} {}{}", value, unit, if value == 1 { "" } else { "s" })
+}
+
+/// Records a run in the task history, deleting the oldest runs of the server beyond `TASK_HISTORY_LIMIT`.
+fn record_task_run(
+    conn: &sqlite::Connection,
+    server_id: u64,
+    schedule: &ServerSchedule,
+    result: TaskResult,
+    message: &str,
+    started_at: u64,
+) -> Result<(), Box<dyn Error>> {
+    let mut statement = conn.prepare(
+        "INSERT INTO server_task_run (server, schedule, name, kind, result, message, started_at, finished_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )?;
+    statement.bind((1, server_id as i64))?;
+    statement.bind((2, schedule.id as i64))?;
+    statement.bind((3, schedule.name.as_str()))?;
+    statement.bind((4, schedule.kind.as_str()))?;
+    statement.bind((5, result.as_str()))?;
+    statement.bind((6, message))?;
+    statement.bind((7, started_at as i64))?;
+    statement.bind((8, now_millis()? as i64))?;
+    statement.next()?;
+
+    let mut statement = conn.prepare(
+        "DELETE FROM server_task_run WHERE server = ? AND id NOT IN (SELECT id FROM server_task_run WHERE server = ? ORDER BY id DESC LIMIT ?)",
+    )?;
+    statement.bind((1, server_id as i64))?;
+    statement.bind((2, server_id as i64))?;
+    statement.bind((3, TASK_HISTORY_LIMIT as i64))?;
+    statement.next()?;
+    Ok(())
+}
+
+fn now_millis() -> Result<u64, Box<dyn Error>> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64)
+}
+
+impl ScheduleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleKind::Command => "command",
+            ScheduleKind::Restart => "restart",
+        }
+    }
+}
+
+impl FromStr for ScheduleKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "command" => Ok(ScheduleKind::Command),
+            "restart" => Ok(ScheduleKind::Restart),
+            _ => Err(format!("Unknown schedule kind {:?}", s)),
+        }
+    }
+}
+
+impl TaskResult {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskResult::Succeeded => "succeeded",
+            TaskResult::Failed => "failed",
+            TaskResult::Skipped => "skipped",
+        }
+    }
+}
+
+impl FromStr for TaskResult {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "succeeded" => Ok(TaskResult::Succeeded),
+            "failed" => Ok(TaskResult::Failed),
+            "skipped" => Ok(TaskResult::Skipped),
+            _ => Err(format!("Unknown task result {:?}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(json: &str) -> Result<ScheduleRequest, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn test_validate_schedule_request() -> Result<(), serde_json::Error> {
+        let restart = request(r#"{"name": "Nightly restart", "kind": "restart", "interval_seconds": 86400}"#)?;
+        assert_eq!(restart.countdown, DEFAULT_RESTART_COUNTDOWN);
+        assert_eq!(restart.validate(), Ok(()));
+        assert!(
+            request(r#"{"name": "Save", "command": "save-all", "interval_seconds": 10, "run_at": "04:00"}"#)?
+                .validate()
+                .is_ok()
+        );
+
+        let invalid = [
+            r#"{"name": "Empty", "command": " ", "interval_seconds": 60}"#,
+            r#"{"name": "Too often", "command": "save-all", "interval_seconds": 9}"#,
+            r#"{"name": "Bad time", "command": "save-all", "interval_seconds": 60, "run_at": "25:00"}"#,
+            r#"{"name": "Zero", "kind": "restart", "interval_seconds": 3600, "countdown": [60, 0]}"#,
+            // The default countdown starts 10 minutes before the restart.
+            r#"{"name": "Too long", "kind": "restart", "interval_seconds": 600}"#,
+            r#"{"name": "Silent", "kind": "restart", "interval_seconds": 3600, "countdown_command": ""}"#,
+        ];
+        for json in invalid {
+            assert!(request(json)?.validate().is_err(), "{} should be invalid", json);
+        }
+        // Restarts don't need a command, and without a countdown they don't need a countdown command.
+        assert!(request(
+            r#"{"name": "Quiet", "kind": "restart", "interval_seconds": 60, "countdown": [], "countdown_command": ""}"#
+        )?
+        .validate()
+        .is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_countdown() {
+        assert_eq!(format_countdown(3600), "1 hour");
+        assert_eq!(format_countdown(7200), "2 hours");
+        assert_eq!(format_countdown(600), "10 minutes");
+        assert_eq!(format_countdown(60), "1 minute");
+        assert_eq!(format_countdown(90), "90 seconds");
+        assert_eq!(format_countdown(1), "1 second");
+        assert_eq!(format_countdown(3660), "61 minutes");
+    }
+
+    #[test]
+    fn test_countdown_warnings() {
+        assert_eq!(
+            countdown_warnings(&DEFAULT_RESTART_COUNTDOWN),
+            vec![(600, 300), (300, 240), (60, 50), (10, 10)]
+        );
+        assert_eq!(countdown_warnings(&[10, 60, 10]), vec![(60, 50), (10, 10)]);
+        assert_eq!(countdown_warnings(&[]), vec![]);
+    }
+}
@@ -15,10 +15,14 @@ pub enum ServerStatus {
     Crashed,
     /// Indicates that the server is in the process of starting
     Starting,
+    /// Indicates that the server didn't become ready before its readiness timeout
+    FailedToStart,
     /// Indicates that the server is in the process of stopping
     Stopping,
     /// Indicates that the server is restarting
     Restarting,
+    /// Indicates that the server was stopped for being idle, and starts when a player tries to join
+    Sleeping,
     /// Indicates that the server is updating its components
     Updating,
     /// Indicates that the server is installing new components
@@ -64,8 +68,10 @@ impl Serialize for ServerStatus {
             ServerStatus::Online => serializer.serialize_str("online"),
             ServerStatus::Crashed => serializer.serialize_str("crashed"),
             ServerStatus::Starting => serializer.serialize_str("starting"),
+            ServerStatus::FailedToStart => serializer.serialize_str("failed_to_start"),
             ServerStatus::Stopping => serializer.serialize_str("stopping"),
             ServerStatus::Restarting => serializer.serialize_str("restarting"),
+            ServerStatus::Sleeping => serializer.serialize_str("sleeping"),
             ServerStatus::Updating => serializer.serialize_str("updating"),
             ServerStatus::Installing => serializer.serialize_str("installing"),
             ServerStatus::Uninstalling => serializer.serialize_str("uninstalling"),
@@ -126,8 +132,10 @@ impl<'de> serde::de::Deserialize<'de> for ServerStatus {
                     "online" => Ok(ServerStatus::Online),
                     "crashed" => Ok(ServerStatus::Crashed),
                     "starting" => Ok(ServerStatus::Starting),
+                    "failed_to_start" => Ok(ServerStatus::FailedToStart),
                     "stopping" => Ok(ServerStatus::Stopping),
                     "restarting" => Ok(ServerStatus::Restarting),
+                    "sleeping" => Ok(ServerStatus::Sleeping),
                     "updating" => Ok(ServerStatus::Updating),
                     "installing" => Ok(ServerStatus::Installing),
                     "uninstalling" => Ok(ServerStatus::Uninstalling),
@@ -160,8 +168,10 @@ impl Display for ServerStatus {
             ServerStatus::Online => "online".to_string(),
             ServerStatus::Crashed => "crashed".to_string(),
             ServerStatus::Starting => "starting".to_string(),
+            ServerStatus::FailedToStart => "failed_to_start".to_string(),
             ServerStatus::Stopping => "stopping".to_string(),
             ServerStatus::Restarting => "restarting".to_string(),
+            ServerStatus::Sleeping => "sleeping".to_string(),
             ServerStatus::Updating => "updating".to_string(),
             ServerStatus::Installing => "installing".to_string(),
             ServerStatus::Uninstalling => "uninstalling".to_string(),
@@ -191,8 +201,10 @@ impl FromStr for ServerStatus {
             "online" => Ok(ServerStatus::Online),
             "crashed" => Ok(ServerStatus::Crashed),
             "starting" => Ok(ServerStatus::Starting),
+            "failed_to_start" => Ok(ServerStatus::FailedToStart),
             "stopping" => Ok(ServerStatus::Stopping),
             "restarting" => Ok(ServerStatus::Restarting),
+            "sleeping" => Ok(ServerStatus::Sleeping),
             "updating" => Ok(ServerStatus::Updating),
             "installing" => Ok(ServerStatus::Installing),
             "uninstalling" => Ok(ServerStatus::Uninstalling),
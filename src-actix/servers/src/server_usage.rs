@@ -0,0 +1,152 @@
+use crate::server_process::running_pid;
+use serde_derive::Serialize;
+use std::collections::HashSet;
+use std::time::Instant;
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, MINIMUM_CPU_UPDATE_INTERVAL};
+
+/// The resources used by a running server, summed over its process and all of its children.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServerUsage {
+    /// The process id of the server.
+    pub pid: u64,
+    /// The number of processes in the server's process tree.
+    pub processes: u64,
+    /// The CPU usage in percent of a single core, so a server using two cores fully reports 200.
+    pub cpu_usage: f32,
+    /// The resident memory in bytes.
+    pub memory_bytes: u64,
+    /// The number of threads, only available on Linux.
+    pub threads: Option<u64>,
+    /// The number of open file descriptors, only available on Linux.
+    pub open_files: Option<u64>,
+    /// The bytes read from disk per second since the previous sample.
+    pub disk_read_bytes_per_second: u64,
+    /// The bytes written to disk per second since the previous sample.
+    pub disk_written_bytes_per_second: u64,
+    /// The bytes read from disk since the server started.
+    pub total_disk_read_bytes: u64,
+    /// The bytes written to disk since the server started.
+    pub total_disk_written_bytes: u64,
+    /// The time since the server started, in seconds.
+    pub uptime_seconds: u64,
+}
+
+/// Samples the resource usage of servers.
+///
+/// CPU usage and disk rates are measured between two samples, so a monitor is meant to be kept
+/// and sampled periodically, like the usage stream does once per second.
+pub struct UsageMonitor {
+    system: System,
+    last_refresh: Option<Instant>,
+}
+
+impl Default for UsageMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsageMonitor {
+    /// Creates a monitor without any samples.
+    pub fn new() -> Self {
+        Self {
+            system: System::new(),
+            last_refresh: None,
+        }
+    }
+
+    /// Refreshes the process list and reads the usage of a server.
+    ///
+    /// # Returns
+    /// `None` if the server isn't running.
+    pub fn sample(&mut self, server_id: u64) -> Option<ServerUsage> {
+        let pid = running_pid(server_id)?;
+        let elapsed = self
+            .last_refresh
+            .map(|last_refresh| last_refresh.elapsed().as_secs_f64())
+            .unwrap_or_default();
+        // Children have to be found through their parents, so every process is refreshed.
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::new().with_cpu().with_memory().with_disk_usage(),
+        );
+        self.last_refresh = Some(Instant::now());
+
+        let root = self.system.process(Pid::from_u32(pid as u32))?;
+        let tree = self.process_tree(root);
+
+        let mut usage = ServerUsage {
+            pid,
+            processes: tree.len() as u64,
+            uptime_seconds: root.run_time(),
+            ..Default::default()
+        };
+        let mut threads = cfg!(target_os = "linux").then_some(0);
+        let mut open_files = cfg!(target_os = "linux").then_some(0);
+        let (mut read_bytes, mut written_bytes) = (0, 0);
+        for process in &tree {
+            let disk_usage = process.disk_usage();
+            usage.cpu_usage += process.cpu_usage();
+            usage.memory_bytes += process.memory();
+            usage.total_disk_read_bytes += disk_usage.total_read_bytes;
+            usage.total_disk_written_bytes += disk_usage.total_written_bytes;
+            read_bytes += disk_usage.read_bytes;
+            written_bytes += disk_usage.written_bytes;
+            if let (Some(threads), Some(tasks)) = (threads.as_mut(), process.tasks()) {
+                // The tasks of a process include its main thread.
+                *threads += (tasks.len() as u64).max(1);
+            }
+            if let Some(open_files) = open_files.as_mut() {
+                *open_files += count_open_files(process.pid());
+            }
+        }
+        usage.threads = threads;
+        usage.open_files = open_files;
+        // The first sample has nothing to compare against.
+        if elapsed > 0.0 {
+            usage.disk_read_bytes_per_second = (read_bytes as f64 / elapsed) as u64;
+            usage.disk_written_bytes_per_second = (written_bytes as f64 / elapsed) as u64;
+        }
+        Some(usage)
+    }
+
+    /// Collects a process and all of its descendants, leaving out threads, which are listed as processes on Linux.
+    fn process_tree<'a>(&'a self, root: &'a Process) -> Vec<&'a Process> {
+        let mut pids = HashSet::from([root.pid()]);
+        let mut tree = vec![root];
+        // Parents can appear after their children in the process list, so repeat until nothing is added.
+        loop {
+            let children: Vec<&Process> = self
+                .system
+                .processes()
+                .values()
+                .filter(|process| process.thread_kind().is_none() && !pids.contains(&process.pid()))
+                .filter(|process| process.parent().is_some_and(|parent| pids.contains(&parent)))
+                .collect();
+            if children.is_empty() {
+                return tree;
+            }
+            pids.extend(children.iter().map(|process| process.pid()));
+            tree.extend(children);
+        }
+    }
+}
+
+/// Reads the usage of a server from two samples taken shortly after each other.
+///
+/// # Returns
+/// `None` if the server isn't running.
+pub fn get_server_usage(server_id: u64) -> Option<ServerUsage> {
+    let mut monitor = UsageMonitor::new();
+    monitor.sample(server_id)?;
+    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+    monitor.sample(server_id)
+}
+
+/// Counts the open file descriptors of a process.
+fn count_open_files(pid: Pid) -> u64 {
+    std::fs::read_dir(format!("/proc/{}/fd", pid))
+        .map(|entries| entries.count() as u64)
+        .unwrap_or_default()
+}
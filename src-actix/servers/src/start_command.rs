@@ -0,0 +1,264 @@
+use crate::jvm_options::java_major_version;
+use crate::server::Server;
+use crate::start_validation::server_port;
+use std::error::Error;
+
+/// The placeholders a start command template can use.
+///
+/// * `{java}` - The Java runtime of the server.
+/// * `{jvm_args}` - The JVM preset, garbage collector and Java arguments, without the heap.
+/// * `{min_ram}` / `{max_ram}` - The heap in MB with its unit, e.g. `-Xmx{max_ram}` becomes `-Xmx4096M`.
+/// * `{jar}` - The start script of the server.
+/// * `{mc_args}` - The Minecraft arguments.
+/// * `{port}` - The `server-port` from `server.properties`.
+///
+/// `{{` and `}}` stand for literal braces.
+pub const PLACEHOLDERS: [&str; 7] = ["java", "jvm_args", "min_ram", "max_ram", "jar", "mc_args", "port"];
+
+/// The value of a placeholder, either one argument or a list that may expand to any number of them.
+enum PlaceholderValue {
+    Single(String),
+    List(Vec<String>),
+}
+
+/// Renders a start command template into the program and its arguments.
+///
+/// The template is split into arguments like a shell would before the placeholders are replaced, so paths
+/// with spaces stay a single argument. A list placeholder that makes up a whole argument, like `{jvm_args}`,
+/// expands into one argument per item, inside a longer argument its items are joined with spaces.
+///
+/// # Arguments
+/// * `server` - The server the placeholders are filled in from.
+/// * `template` - The template, e.g. `{java} {jvm_args} -Xmx{max_ram} -jar {jar} {mc_args}`.
+pub fn render_start_command(server: &Server<u64>, template: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    render(template, &|name| placeholder_value(server, name))
+}
+
+/// Checks that a template can be parsed and only uses known placeholders, without filling them in.
+pub fn check_start_command(template: &str) -> Result<(), Box<dyn Error>> {
+    render(template, &|name| match PLACEHOLDERS.contains(&name) {
+        true => Ok(PlaceholderValue::Single(String::new())),
+        false => Err(unknown_placeholder(name)),
+    })
+    .map(|_| ())
+}
+
+/// Splits a template into arguments and replaces the placeholders with the values `lookup` returns.
+fn render(
+    template: &str,
+    lookup: &dyn Fn(&str) -> Result<PlaceholderValue, Box<dyn Error>>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let tokens = shell_words::split(template).map_err(|e| format!("The start command can't be parsed: {}", e))?;
+    let mut args = Vec::new();
+    for token in tokens {
+        let whole_placeholder = token
+            .strip_prefix('{')
+            .and_then(|token| token.strip_suffix('}'))
+            .filter(|name| !name.contains(['{', '}']));
+        match whole_placeholder {
+            Some(name) => match lookup(name)? {
+                PlaceholderValue::Single(value) => args.push(value),
+                PlaceholderValue::List(values) => args.extend(values),
+            },
+            None => args.push(render_token(&token, lookup)?),
+        }
+    }
+    if args.is_empty() {
+        return Err("The start command is empty".into());
+    }
+    Ok(args)
+}
+
+/// Lists the placeholders a template uses, to only validate the settings that end up in the command.
+pub fn used_placeholders(template: &str) -> Vec<&'static str> {
+    PLACEHOLDERS
+        .into_iter()
+        .filter(|name| template.contains(&format!("{{{}}}", name)))
+        .collect()
+}
+
+/// Checks that an environment variable can be passed to the server process.
+///
+/// # Returns
+/// A description of the problem if the variable is invalid.
+pub fn check_environment_variable(name: &str, value: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Environment variable names can't be empty".to_string());
+    }
+    if name.contains('=') || name.contains('\0') {
+        return Err(format!(
+            "The environment variable name {:?} can't contain '=' or NUL",
+            name
+        ));
+    }
+    if value.contains('\0') {
+        return Err(format!(
+            "The value of the environment variable {} can't contain NUL",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Replaces the placeholders in a single argument of the template.
+fn render_token(
+    token: &str,
+    lookup: &dyn Fn(&str) -> Result<PlaceholderValue, Box<dyn Error>>,
+) -> Result<String, Box<dyn Error>> {
+    let mut rendered = String::new();
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let rest = chars.as_str();
+                if let Some(rest) = rest.strip_prefix('{') {
+                    rendered.push('{');
+                    chars = rest.chars();
+                    continue;
+                }
+                let (name, rest) = rest
+                    .split_once('}')
+                    .ok_or_else(|| format!("Unclosed placeholder in {:?}", token))?;
+                match lookup(name)? {
+                    PlaceholderValue::Single(value) => rendered.push_str(&value),
+                    PlaceholderValue::List(values) => rendered.push_str(&values.join(" ")),
+                }
+                chars = rest.chars();
+            }
+            '}' => {
+                let rest = chars.as_str();
+                let rest = rest
+                    .strip_prefix('}')
+                    .ok_or_else(|| format!("Unmatched '}}' in {:?}, use '}}}}' for a literal brace", token))?;
+                rendered.push('}');
+                chars = rest.chars();
+            }
+            c => rendered.push(c),
+        }
+    }
+    Ok(rendered)
+}
+
+/// Looks up the value of a placeholder for a server.
+fn placeholder_value(server: &Server<u64>, name: &str) -> Result<PlaceholderValue, Box<dyn Error>> {
+    Ok(match name {
+        "java" => PlaceholderValue::Single(
+            server
+                .java_runtime
+                .as_ref()
+                .filter(|java_runtime| !java_runtime.as_os_str().is_empty())
+                .ok_or("Java runtime not set")?
+                .to_str()
+                .ok_or("Invalid Java runtime path")?
+                .to_string(),
+        ),
+        "jvm_args" => {
+            let java_version = server.java_runtime.as_deref().and_then(java_major_version);
+            let mut args = server.jvm_options.build_flags(server.max_ram, java_version);
+            if let Some(java_arguments) = &server.java_arguments {
+                args.extend(shell_words::split(java_arguments).map_err(|_| "Invalid Java arguments")?);
+            }
+            PlaceholderValue::List(args)
+        }
+        "min_ram" => PlaceholderValue::Single(format!("{}M", server.min_ram)),
+        "max_ram" => PlaceholderValue::Single(format!("{}M", server.max_ram)),
+        "jar" => PlaceholderValue::Single(
+            server
+                .start_script
+                .as_ref()
+                .ok_or("Start script not set")?
+                .to_str()
+                .ok_or("Invalid start script path")?
+                .to_string(),
+        ),
+        "mc_args" => PlaceholderValue::List(match &server.minecraft_arguments {
+            Some(minecraft_arguments) => {
+                shell_words::split(minecraft_arguments).map_err(|_| "Invalid Minecraft arguments")?
+            }
+            None => Vec::new(),
+        }),
+        "port" => PlaceholderValue::Single(server_port(server).to_string()),
+        name => return Err(unknown_placeholder(name)),
+    })
+}
+
+/// The error for a placeholder that isn't in `PLACEHOLDERS`.
+fn unknown_placeholder(name: &str) -> Box<dyn Error> {
+    format!(
+        "Unknown placeholder {{{}}}, expected one of {{{}}}",
+        name,
+        PLACEHOLDERS.join("}, {")
+    )
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jvm_options::{GarbageCollector, JvmOptions};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_render_start_command() -> Result<(), Box<dyn Error>> {
+        let server = Server {
+            min_ram: 1024,
+            max_ram: 4096,
+            java_runtime: Some(PathBuf::from("/opt/java 21/bin/java")),
+            java_arguments: Some("-Dfile.encoding=UTF-8 \"-Dname=two words\"".to_string()),
+            jvm_options: JvmOptions {
+                gc: Some(GarbageCollector::G1),
+                ..JvmOptions::default()
+            },
+            start_script: Some(PathBuf::from("server.jar")),
+            minecraft_arguments: Some("nogui".to_string()),
+            ..Server::default()
+        };
+        assert_eq!(
+            render_start_command(
+                &server,
+                "{java} {jvm_args} -Xms{min_ram} -Xmx{max_ram} -jar {jar} {mc_args}"
+            )?,
+            vec![
+                "/opt/java 21/bin/java",
+                "-XX:+UseG1GC",
+                "-Dfile.encoding=UTF-8",
+                "-Dname=two words",
+                "-Xms1024M",
+                "-Xmx4096M",
+                "-jar",
+                "server.jar",
+                "nogui",
+            ]
+        );
+        // Inside a longer argument a list is joined, and doubled braces are literal.
+        assert_eq!(
+            render_start_command(&server, "sh -c '{{exec}} {java} {mc_args}'")?,
+            vec!["sh", "-c", "{exec} /opt/java 21/bin/java nogui"]
+        );
+        assert!(render_start_command(&Server::default(), "{java} -jar server.jar").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_start_command() {
+        assert!(check_start_command("{java} -Xmx{max_ram} -jar {jar} --port {port}").is_ok());
+        assert!(check_start_command("{java} -jar {server_jar}").is_err());
+        assert!(check_start_command("{java -jar server.jar").is_err());
+        assert!(check_start_command("java }").is_err());
+        assert!(check_start_command("java 'unclosed").is_err());
+        assert!(check_start_command("  ").is_err());
+        assert_eq!(
+            used_placeholders("{java} -Xmx{max_ram} -jar server.jar"),
+            vec!["java", "max_ram"]
+        );
+    }
+
+    #[test]
+    fn test_check_environment_variable() {
+        assert!(check_environment_variable("JAVA_TOOL_OPTIONS", "-Xss4M").is_ok());
+        assert!(check_environment_variable("EMPTY", "").is_ok());
+        assert!(check_environment_variable("", "value").is_err());
+        assert!(check_environment_variable("A=B", "value").is_err());
+        assert!(check_environment_variable("NAME", "a\0b").is_err());
+    }
+}
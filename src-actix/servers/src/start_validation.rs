@@ -0,0 +1,594 @@
+use crate::jvm_options::{java_major_version, GarbageCollector, REMOVED_FLAGS};
+use crate::server::Server;
+use crate::server_database::ServerDatabase;
+use crate::server_eula::is_eula_accepted;
+use crate::server_process::is_running;
+use crate::server_properties::ServerProperties;
+use crate::start_command::{check_environment_variable, render_start_command, used_placeholders};
+use crate::start_executable_type::{StartExecutableType, StartExecutableTypeExt};
+use serde_derive::Serialize;
+use std::fmt::Display;
+use std::net::TcpListener;
+use std::path::Path;
+
+/// The port Minecraft listens on when `server-port` isn't set.
+const DEFAULT_SERVER_PORT: u16 = 25565;
+
+/// The part of the server's setup a validation issue is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ValidationCheck {
+    /// The `server-port` from `server.properties`.
+    Port,
+    /// The acceptance of the Minecraft EULA in `eula.txt`.
+    Eula,
+    /// The Java runtime the server jar is started with.
+    JavaRuntime,
+    /// The start script, jar or executable of the server.
+    StartScript,
+    /// The memory the server is allowed to use.
+    Memory,
+    /// The JVM preset, garbage collector and Java arguments.
+    JvmOptions,
+    /// The start command template that replaces the start script.
+    StartCommand,
+    /// The environment variables of the server process.
+    Environment,
+}
+
+/// How serious a validation issue is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationSeverity {
+    /// The server can't start until the issue is fixed.
+    Error,
+    /// The server can start, but might run into problems.
+    Warning,
+}
+
+/// A problem found while validating a server before it starts.
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationIssue {
+    /// What the issue is about.
+    pub check: ValidationCheck,
+    /// Whether the issue prevents the server from starting.
+    pub severity: ValidationSeverity,
+    /// A description of the issue.
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn error(check: ValidationCheck, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            severity: ValidationSeverity::Error,
+            message: message.into(),
+        }
+    }
+
+    fn warning(check: ValidationCheck, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            severity: ValidationSeverity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+/// The error returned when a server can't start because of validation errors.
+#[derive(Debug, Clone, Serialize)]
+pub struct StartValidationError {
+    /// Every issue found, including the warnings.
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl Display for StartValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors: Vec<&str> = self
+            .issues
+            .iter()
+            .filter(|issue| issue.severity == ValidationSeverity::Error)
+            .map(|issue| issue.message.as_str())
+            .collect();
+        write!(f, "The server can't start: {}", errors.join(", "))
+    }
+}
+
+impl StartValidationError {
+    /// Checks if one of the errors is about a specific part of the setup.
+    pub fn has_error(&self, check: ValidationCheck) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.check == check && issue.severity == ValidationSeverity::Error)
+    }
+}
+
+impl std::error::Error for StartValidationError {}
+
+pub trait StartValidation {
+    /// Checks that the server can start, without starting it.
+    ///
+    /// # Returns
+    /// Every issue found, an empty list if the server is ready to start.
+    fn validate_start(&self) -> Vec<ValidationIssue>;
+
+    /// Checks that the server can start.
+    ///
+    /// # Errors
+    /// Returns a `StartValidationError` with every issue if any of them is an error.
+    fn ensure_can_start(&self) -> Result<(), StartValidationError> {
+        into_result(self.validate_start())
+    }
+
+    /// Checks that the running server can start again once it's stopped, without stopping it.
+    ///
+    /// The port and heap of the running server are counted as free, since they're released when it stops.
+    fn validate_restart(&self) -> Vec<ValidationIssue>;
+
+    /// Checks that the running server can start again once it's stopped.
+    ///
+    /// # Errors
+    /// Returns a `StartValidationError` with every issue if any of them is an error.
+    fn ensure_can_restart(&self) -> Result<(), StartValidationError> {
+        into_result(self.validate_restart())
+    }
+}
+
+/// Turns the issues found into an error if any of them prevents the server from starting.
+fn into_result(issues: Vec<ValidationIssue>) -> Result<(), StartValidationError> {
+    if issues.iter().any(|issue| issue.severity == ValidationSeverity::Error) {
+        return Err(StartValidationError { issues });
+    }
+    Ok(())
+}
+
+impl StartValidation for Server<u64> {
+    fn validate_start(&self) -> Vec<ValidationIssue> {
+        validate_server(self, false)
+    }
+
+    fn validate_restart(&self) -> Vec<ValidationIssue> {
+        validate_server(self, true)
+    }
+}
+
+/// Runs every check that applies to the way the server is started.
+///
+/// # Arguments
+/// * `restarting` - Whether the server is still running and about to be restarted.
+fn validate_server(server: &Server<u64>, restarting: bool) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    validate_port(server, restarting, &mut issues);
+    validate_eula(&server.directory, &mut issues);
+    validate_environment(server, &mut issues);
+    // The heap of the running server is given back to the host when it stops.
+    let freed_ram = if restarting { server.max_ram } else { 0 };
+
+    if let Some(template) = &server.start_command {
+        validate_start_command(server, template, freed_ram, &mut issues);
+        return issues;
+    }
+
+    let Some(start_script) = &server.start_script else {
+        issues.push(ValidationIssue::error(
+            ValidationCheck::StartScript,
+            "Start script not set",
+        ));
+        return issues;
+    };
+    if !server.directory.join(start_script).is_file() {
+        issues.push(ValidationIssue::error(
+            ValidationCheck::StartScript,
+            format!("Start script {:?} doesn't exist", start_script),
+        ));
+    }
+    match StartExecutableType::from_path(start_script) {
+        // The Java runtime and memory settings are only used to start jars.
+        Ok(StartExecutableType::Jar) => {
+            validate_java_runtime(server.java_runtime.as_deref(), &mut issues);
+            validate_memory(server.min_ram, server.max_ram, freed_ram, &mut issues);
+            validate_jvm_options(server, &mut issues);
+        }
+        Ok(_) => {}
+        Err(e) => issues.push(ValidationIssue::error(ValidationCheck::StartScript, e.to_string())),
+    }
+    issues
+}
+
+/// Checks that the start command template renders and that the settings it uses are valid.
+fn validate_start_command(server: &Server<u64>, template: &str, freed_ram: u64, issues: &mut Vec<ValidationIssue>) {
+    let placeholders = used_placeholders(template);
+    let error_count = |issues: &[ValidationIssue]| {
+        issues
+            .iter()
+            .filter(|issue| issue.severity == ValidationSeverity::Error)
+            .count()
+    };
+    let known_errors = error_count(issues);
+    if placeholders.contains(&"java") {
+        validate_java_runtime(server.java_runtime.as_deref(), issues);
+    }
+    if placeholders.contains(&"min_ram") || placeholders.contains(&"max_ram") {
+        validate_memory(server.min_ram, server.max_ram, freed_ram, issues);
+    }
+    if placeholders.contains(&"jvm_args") {
+        validate_jvm_options(server, issues);
+    }
+    if let Some(start_script) = server.start_script.as_ref().filter(|_| placeholders.contains(&"jar")) {
+        if !server.directory.join(start_script).is_file() {
+            issues.push(ValidationIssue::error(
+                ValidationCheck::StartScript,
+                format!("Start script {:?} doesn't exist", start_script),
+            ));
+        }
+    }
+
+    let args = match render_start_command(server, template) {
+        Ok(args) => args,
+        Err(e) => {
+            // The settings the template failed on, like a missing Java runtime, are already reported.
+            if error_count(issues) == known_errors {
+                issues.push(ValidationIssue::error(ValidationCheck::StartCommand, e.to_string()));
+            }
+            return;
+        }
+    };
+    // The program is looked up in the PATH unless it's a path, which is relative to the server's directory.
+    let program = Path::new(&args[0]);
+    let found = if program.components().count() == 1 {
+        std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).any(|directory| directory.join(program).is_file()))
+            .unwrap_or(false)
+    } else {
+        server.directory.join(program).is_file()
+    };
+    if !found {
+        issues.push(ValidationIssue::error(
+            ValidationCheck::StartCommand,
+            format!("The program {:?} of the start command doesn't exist", program),
+        ));
+    }
+}
+
+/// Checks that the environment variables can be passed to the server process.
+fn validate_environment(server: &Server<u64>, issues: &mut Vec<ValidationIssue>) {
+    for (name, value) in &server.environment {
+        if let Err(e) = check_environment_variable(name, value) {
+            issues.push(ValidationIssue::error(ValidationCheck::Environment, e));
+        }
+    }
+}
+
+/// Checks that the server's port is free and not used by another server.
+///
+/// # Arguments
+/// * `restarting` - Whether the server is about to be restarted, so its own port is still bound.
+fn validate_port(server: &Server<u64>, restarting: bool, issues: &mut Vec<ValidationIssue>) {
+    let port = server_port(server);
+
+    match Server::get_list_of_servers() {
+        Ok(servers) => {
+            for other in servers.iter().filter(|other| other.id != server.id) {
+                if server_port(other) != port {
+                    continue;
+                }
+                let message = format!("Port {} is also used by the server {:?}", port, other.name);
+                issues.push(if is_running(other.id) {
+                    ValidationIssue::error(ValidationCheck::Port, message)
+                } else {
+                    ValidationIssue::warning(ValidationCheck::Port, message)
+                });
+            }
+        }
+        Err(e) => issues.push(ValidationIssue::warning(
+            ValidationCheck::Port,
+            format!("Failed to list the other servers: {}", e),
+        )),
+    }
+
+    // A running server would find its own port bound.
+    if !restarting && !is_running(server.id) && TcpListener::bind(("0.0.0.0", port)).is_err() {
+        issues.push(ValidationIssue::error(
+            ValidationCheck::Port,
+            format!("Port {} is already in use", port),
+        ));
+    }
+}
+
+/// Reads the port a server listens on from its `server.properties`.
+pub(crate) fn server_port(server: &Server<u64>) -> u16 {
+    server
+        .get_property("server-port")
+        .ok()
+        .and_then(|port| port.trim().parse().ok())
+        .unwrap_or(DEFAULT_SERVER_PORT)
+}
+
+/// Checks that the Minecraft EULA has been accepted in the server's `eula.txt`.
+fn validate_eula(directory: &Path, issues: &mut Vec<ValidationIssue>) {
+    if !is_eula_accepted(directory) {
+        issues.push(ValidationIssue::error(
+            ValidationCheck::Eula,
+            "The Minecraft EULA hasn't been accepted in eula.txt",
+        ));
+    }
+}
+
+/// Checks that the Java runtime exists and is executable.
+fn validate_java_runtime(java_runtime: Option<&Path>, issues: &mut Vec<ValidationIssue>) {
+    let Some(java_runtime) = java_runtime.filter(|java_runtime| !java_runtime.as_os_str().is_empty()) else {
+        issues.push(ValidationIssue::error(
+            ValidationCheck::JavaRuntime,
+            "Java runtime not set",
+        ));
+        return;
+    };
+
+    // A bare command like `java` is looked up in the PATH.
+    let candidates: Vec<std::path::PathBuf> = if java_runtime.components().count() == 1 {
+        std::env::var_os("PATH")
+            .map(|path| {
+                std::env::split_paths(&path)
+                    .map(|directory| directory.join(java_runtime))
+                    .collect()
+            })
+            .unwrap_or_default()
+    } else {
+        vec![java_runtime.to_path_buf()]
+    };
+
+    match candidates.iter().find(|candidate| candidate.is_file()) {
+        None => issues.push(ValidationIssue::error(
+            ValidationCheck::JavaRuntime,
+            format!("Java runtime {:?} doesn't exist", java_runtime),
+        )),
+        Some(candidate) if !is_executable(candidate) => issues.push(ValidationIssue::error(
+            ValidationCheck::JavaRuntime,
+            format!("Java runtime {:?} isn't executable", candidate),
+        )),
+        Some(_) => {}
+    }
+}
+
+/// Checks if a file has any of the execute permission bits set.
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Every file can be run on platforms without execute permissions.
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+/// Checks that the heap of the server is consistent and fits in the memory available on the host.
+///
+/// # Arguments
+/// * `min_ram` - The initial heap in MB, as passed to `-Xms`.
+/// * `max_ram` - The maximum heap in MB, as passed to `-Xmx`.
+/// * `freed_ram` - The memory in MB that becomes available before the server starts, e.g. the heap of the server
+///   being restarted.
+fn validate_memory(min_ram: u64, max_ram: u64, freed_ram: u64, issues: &mut Vec<ValidationIssue>) {
+    if max_ram > 0 && min_ram > max_ram {
+        issues.push(ValidationIssue::error(
+            ValidationCheck::Memory,
+            format!(
+                "The minimum memory of {} MB is more than the maximum of {} MB",
+                min_ram, max_ram
+            ),
+        ));
+    }
+
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+    let available = system.available_memory() + freed_ram.saturating_mul(1024 * 1024);
+    let required = max_ram.saturating_mul(1024 * 1024);
+    if required > available {
+        issues.push(ValidationIssue::error(
+            ValidationCheck::Memory,
+            format!(
+                "The maximum memory of {} MB doesn't fit in the {} MB available on the host",
+                max_ram,
+                available / (1024 * 1024)
+            ),
+        ));
+    }
+}
+
+/// Checks the JVM preset, garbage collector and Java arguments against the Java version of the runtime.
+fn validate_jvm_options(server: &Server<u64>, issues: &mut Vec<ValidationIssue>) {
+    let options = &server.jvm_options;
+    let java_version = server.java_runtime.as_deref().and_then(java_major_version);
+    let arguments = match server.java_arguments.as_deref().map(shell_words::split).transpose() {
+        Ok(arguments) => arguments.unwrap_or_default(),
+        Err(e) => {
+            issues.push(ValidationIssue::error(
+                ValidationCheck::JvmOptions,
+                format!("The Java arguments can't be parsed: {}", e),
+            ));
+            Vec::new()
+        }
+    };
+
+    if let (Some(preset_gc), Some(gc)) = (options.preset.gc(), options.gc) {
+        if preset_gc != gc {
+            issues.push(ValidationIssue::warning(
+                ValidationCheck::JvmOptions,
+                format!(
+                    "The {:?} preset uses {:?}, the {:?} collector is ignored",
+                    options.preset, preset_gc, gc
+                ),
+            ));
+        }
+    }
+
+    if let Some(gc) = options.selected_gc() {
+        match java_version {
+            Some(version) if version < gc.min_java_version() => issues.push(ValidationIssue::error(
+                ValidationCheck::JvmOptions,
+                format!(
+                    "The {:?} collector needs Java {} or newer, the runtime is Java {}",
+                    gc,
+                    gc.min_java_version(),
+                    version
+                ),
+            )),
+            Some(_) => {}
+            None => issues.push(ValidationIssue::warning(
+                ValidationCheck::JvmOptions,
+                format!("The Java version couldn't be detected to check the {:?} collector", gc),
+            )),
+        }
+        if gc == GarbageCollector::Shenandoah {
+            issues.push(ValidationIssue::warning(
+                ValidationCheck::JvmOptions,
+                "Shenandoah isn't included in every Java build, e.g. not in Oracle's",
+            ));
+        }
+        // The JVM refuses to start with more than one collector.
+        let gc_flags = gc.flags(java_version);
+        if let Some(flag) = arguments
+            .iter()
+            .find(|arg| arg.starts_with("-XX:+Use") && arg.ends_with("GC") && !gc_flags.contains(&arg.as_str()))
+        {
+            issues.push(ValidationIssue::error(
+                ValidationCheck::JvmOptions,
+                format!("The Java argument {} selects a second garbage collector", flag),
+            ));
+        }
+    }
+
+    for argument in &arguments {
+        if argument.starts_with("-Xmx") || argument.starts_with("-Xms") {
+            issues.push(ValidationIssue::warning(
+                ValidationCheck::JvmOptions,
+                format!("The Java argument {} overrides the memory settings", argument),
+            ));
+        }
+        let removed = REMOVED_FLAGS
+            .iter()
+            .find(|(flag, _)| argument == flag || argument.starts_with(&format!("{}=", flag)));
+        if let (Some((flag, removed_in)), Some(version)) = (removed, java_version) {
+            if version >= *removed_in {
+                issues.push(ValidationIssue::error(
+                    ValidationCheck::JvmOptions,
+                    format!(
+                        "The Java argument {} was removed in Java {}, the runtime is Java {}",
+                        flag, removed_in, version
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jvm_options::{JvmOptions, JvmPreset};
+
+    fn checks(issues: &[ValidationIssue]) -> Vec<(ValidationCheck, ValidationSeverity)> {
+        issues.iter().map(|issue| (issue.check, issue.severity)).collect()
+    }
+
+    #[test]
+    fn test_validate_memory() {
+        let mut issues = Vec::new();
+        validate_memory(2048, 1024, 0, &mut issues);
+        assert_eq!(
+            checks(&issues),
+            vec![(ValidationCheck::Memory, ValidationSeverity::Error)]
+        );
+
+        // No host has an exabyte available, unless the server being restarted held it.
+        let exabyte = 1 << 40;
+        let mut issues = Vec::new();
+        validate_memory(0, exabyte, 0, &mut issues);
+        assert_eq!(
+            checks(&issues),
+            vec![(ValidationCheck::Memory, ValidationSeverity::Error)]
+        );
+        let mut issues = Vec::new();
+        validate_memory(0, exabyte, exabyte, &mut issues);
+        assert!(issues.is_empty());
+    }
+
+    #[test]
+    fn test_validate_java_runtime() -> Result<(), Box<dyn std::error::Error>> {
+        let mut issues = Vec::new();
+        validate_java_runtime(None, &mut issues);
+        validate_java_runtime(Some(Path::new("")), &mut issues);
+        validate_java_runtime(Some(Path::new("/nonexistent/bin/java")), &mut issues);
+        assert_eq!(
+            checks(&issues),
+            vec![(ValidationCheck::JavaRuntime, ValidationSeverity::Error); 3]
+        );
+
+        // A bare command is looked up in the PATH.
+        let mut issues = Vec::new();
+        validate_java_runtime(Some(Path::new("sh")), &mut issues);
+        assert!(issues.is_empty());
+
+        let directory = std::env::temp_dir().join(format!("start_validation_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory)?;
+        let java = directory.join("java");
+        std::fs::write(&java, "")?;
+        let mut issues = Vec::new();
+        validate_java_runtime(Some(&java), &mut issues);
+        std::fs::remove_dir_all(&directory)?;
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| issue.message.contains("isn't executable"))
+                .collect::<Vec<_>>(),
+            vec![cfg!(unix)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_jvm_options() {
+        let server = Server {
+            java_runtime: None,
+            java_arguments: Some("-XX:+UseZGC -Xmx2G".to_string()),
+            jvm_options: JvmOptions {
+                preset: JvmPreset::Aikar,
+                gc: Some(GarbageCollector::Zgc),
+            },
+            ..Server::default()
+        };
+        let mut issues = Vec::new();
+        validate_jvm_options(&server, &mut issues);
+        assert_eq!(
+            checks(&issues),
+            vec![
+                // The preset picks G1 over the collector.
+                (ValidationCheck::JvmOptions, ValidationSeverity::Warning),
+                // The runtime's version is unknown.
+                (ValidationCheck::JvmOptions, ValidationSeverity::Warning),
+                // -XX:+UseZGC next to the preset's G1.
+                (ValidationCheck::JvmOptions, ValidationSeverity::Error),
+                // -Xmx2G next to the memory settings.
+                (ValidationCheck::JvmOptions, ValidationSeverity::Warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validation_error() {
+        let warning = ValidationIssue::warning(ValidationCheck::JvmOptions, "Might not work");
+        assert!(into_result(vec![warning.clone()]).is_ok());
+
+        let error = ValidationIssue::error(ValidationCheck::Eula, "The EULA hasn't been accepted");
+        let Err(e) = into_result(vec![warning, error]) else {
+            unreachable!("An error must fail the validation");
+        };
+        assert!(e.has_error(ValidationCheck::Eula));
+        assert!(!e.has_error(ValidationCheck::JvmOptions));
+        assert_eq!(e.to_string(), "The server can't start: The EULA hasn't been accepted");
+    }
+}
@@ -0,0 +1,268 @@
+use crate::idle_policy::IdlePolicy;
+use crate::server::Server;
+use crate::server_database::ServerDatabase;
+use crate::server_list_ping::{read_var_int, utf16_be, write_packet, write_string, write_var_int};
+use crate::server_process::{is_running, update_server_status, ServerProcess};
+use crate::server_properties::ServerProperties;
+use crate::server_status::ServerStatus;
+use crate::start_validation::server_port;
+use lazy_static::lazy_static;
+use log::{debug, info, warn};
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How long the listener waits for a client to send its next packet.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(3);
+/// How often the listener checks if it should stop while nobody connects.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// The largest packet accepted, handshakes and status requests are only a few bytes long.
+const MAX_PACKET_LENGTH: i32 = 1024;
+/// The handshake state of a status request, every other state is a login attempt.
+const STATUS_NEXT_STATE: i32 = 1;
+/// The protocol version reported to legacy clients, any version they don't know shows the version name.
+const LEGACY_PROTOCOL_VERSION: u32 = 127;
+/// The version name shown to clients in the server list.
+const SLEEPING_VERSION_NAME: &str = "Sleeping";
+/// The `max-players` Minecraft defaults to.
+const DEFAULT_MAX_PLAYERS: u32 = 20;
+
+/// A listener holding the port of a sleeping server.
+struct WakeListener {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+lazy_static! {
+    /// The listeners of the sleeping servers, by server id.
+    static ref WAKE_LISTENERS: Mutex<HashMap<u64, WakeListener>> = Mutex::new(HashMap::new());
+}
+
+/// Listens on the port of a sleeping server, answering pings with the MOTD of its idle policy
+/// and starting the server when a player tries to join.
+pub fn start_wake_listener(server: &Server<u64>) -> Result<(), Box<dyn Error>> {
+    stop_wake_listener(server.id)?;
+    let port = server_port(server);
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    // Accepting doesn't block, so the listener notices when it's asked to stop.
+    listener.set_nonblocking(true)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let server_id = server.id;
+    let policy = server.idle_policy.clone();
+    let max_players = server
+        .get_property("max-players")
+        .ok()
+        .and_then(|max| max.trim().parse().ok())
+        .unwrap_or(DEFAULT_MAX_PLAYERS);
+    let handle = thread::spawn({
+        let stop = stop.clone();
+        move || {
+            if listen(listener, &stop, &policy, max_players) {
+                // Starting the server stops this listener and waits for it, so it's done from another thread.
+                thread::spawn(move || wake_server(server_id));
+            }
+        }
+    });
+
+    WAKE_LISTENERS
+        .lock()
+        .map_err(|_| "Failed to lock the wake listeners")?
+        .insert(server_id, WakeListener { stop, handle });
+    info!("Listening on port {} to wake server {:?}", port, server.name);
+    Ok(())
+}
+
+/// Stops listening on the port of a server and waits until the port is released.
+///
+/// # Returns
+/// `false` if the server had no listener.
+pub fn stop_wake_listener(server_id: u64) -> Result<bool, Box<dyn Error>> {
+    let listener = WAKE_LISTENERS
+        .lock()
+        .map_err(|_| "Failed to lock the wake listeners")?
+        .remove(&server_id);
+    let Some(listener) = listener else {
+        return Ok(false);
+    };
+    listener.stop.store(true, Ordering::SeqCst);
+    listener
+        .handle
+        .join()
+        .map_err(|_| "The wake listener thread panicked")?;
+    debug!("Stopped the wake listener of server {}", server_id);
+    Ok(true)
+}
+
+/// Checks if the panel listens for the players of a sleeping server.
+fn is_listening(server_id: u64) -> bool {
+    WAKE_LISTENERS
+        .lock()
+        .map(|listeners| listeners.contains_key(&server_id))
+        .unwrap_or(false)
+}
+
+/// Stops the listeners of every sleeping server, for the panel shutting down.
+pub fn stop_all_wake_listeners() {
+    let server_ids: Vec<u64> = WAKE_LISTENERS
+        .lock()
+        .map(|listeners| listeners.keys().copied().collect())
+        .unwrap_or_default();
+    for server_id in server_ids {
+        if let Err(e) = stop_wake_listener(server_id) {
+            warn!("Failed to stop the wake listener of server {}: {}", server_id, e);
+        }
+    }
+}
+
+/// Applies a changed idle policy to the listener of a sleeping server.
+pub fn update_wake_listener(server: &Server<u64>) -> Result<(), Box<dyn Error>> {
+    if server.status != Some(ServerStatus::Sleeping) || is_running(server.id) {
+        return Ok(());
+    }
+    match server.idle_policy.wake_on_connect {
+        true => start_wake_listener(server),
+        false => stop_wake_listener(server.id).map(|_| ()),
+    }
+}
+
+/// Listens again for the servers that were sleeping when the panel shut down.
+pub fn resume_wake_listeners() -> Result<(), Box<dyn Error>> {
+    for server in Server::get_list_of_servers()? {
+        if server.status == Some(ServerStatus::Sleeping) && server.idle_policy.wake_on_connect && !is_running(server.id)
+        {
+            if let Err(e) = start_wake_listener(&server) {
+                warn!(
+                    "Failed to listen for players of sleeping server {:?}: {}",
+                    server.name, e
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Accepts connections until a player tries to join or the listener is stopped.
+///
+/// # Returns
+/// `true` if a player tried to join.
+fn listen(listener: TcpListener, stop: &AtomicBool, policy: &IdlePolicy, max_players: u32) -> bool {
+    while !stop.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, address)) => match answer(stream, policy, max_players) {
+                Ok(true) => {
+                    info!("{} tried to join, waking the server", address);
+                    return true;
+                }
+                Ok(false) => {}
+                Err(e) => debug!("Failed to answer {} while sleeping: {}", address, e),
+            },
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL_INTERVAL),
+            Err(e) => {
+                warn!("Failed to accept a connection while sleeping: {}", e);
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+        }
+    }
+    false
+}
+
+/// Answers a ping with the sleeping MOTD, or disconnects a player trying to join.
+///
+/// # Returns
+/// `true` if the client tried to join.
+fn answer(mut stream: TcpStream, policy: &IdlePolicy, max_players: u32) -> Result<bool, Box<dyn Error>> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    // Clients before 1.7 start with 0xFE instead of the length of a handshake.
+    let mut first = [0u8];
+    stream.read_exact(&mut first)?;
+    if first[0] == 0xFE {
+        answer_legacy_ping(&mut stream, policy, max_players)?;
+        return Ok(false);
+    }
+
+    let handshake = read_packet(&mut first.as_slice().chain(&stream))?;
+    let mut handshake = handshake.as_slice();
+    if read_var_int(&mut handshake)? != 0x00 {
+        return Err("Expected a handshake".into());
+    }
+    let protocol = read_var_int(&mut handshake)?;
+    // The address and port the client connected to aren't needed.
+    let address_length = read_var_int(&mut handshake)?.max(0) as usize;
+    let mut handshake = handshake.get(address_length + 2..).ok_or("Handshake is too short")?;
+    if read_var_int(&mut handshake)? != STATUS_NEXT_STATE {
+        // The login disconnect packet has the id 0x00 and a JSON chat component as its reason.
+        let mut disconnect = Vec::new();
+        write_var_int(&mut disconnect, 0x00);
+        write_string(&mut disconnect, &json!({ "text": policy.kick_message }).to_string());
+        write_packet(&mut stream, &disconnect)?;
+        return Ok(true);
+    }
+
+    // The status request is an empty packet with the id 0x00.
+    read_packet(&mut stream)?;
+    let status = json!({
+        // Reporting the client's own protocol keeps it from showing the server as outdated.
+        "version": { "name": SLEEPING_VERSION_NAME, "protocol": protocol },
+        "players": { "max": max_players, "online": 0 },
+        "description": { "text": policy.motd },
+    });
+    let mut response = Vec::new();
+    write_var_int(&mut response, 0x00);
+    write_string(&mut response, &status.to_string());
+    write_packet(&mut stream, &response)?;
+
+    // The client measures the latency with a ping, which is answered with the same payload.
+    let ping = read_packet(&mut stream)?;
+    write_packet(&mut stream, &ping)?;
+    stream.flush()?;
+    Ok(false)
+}
+
+/// Answers the ping of a client before 1.7 with a kick packet holding the status.
+fn answer_legacy_ping(stream: &mut TcpStream, policy: &IdlePolicy, max_players: u32) -> Result<(), Box<dyn Error>> {
+    let status = format!(
+        "\u{a7}1\0{}\0{}\0{}\0{}\0{}",
+        LEGACY_PROTOCOL_VERSION, SLEEPING_VERSION_NAME, policy.motd, 0, max_players
+    );
+    let mut response = vec![0xFF];
+    response.extend_from_slice(&(status.encode_utf16().count() as u16).to_be_bytes());
+    response.extend_from_slice(&utf16_be(&status));
+    stream.write_all(&response)?;
+    Ok(())
+}
+
+/// Reads a packet prefixed with its length.
+fn read_packet(reader: &mut impl Read) -> Result<Vec<u8>, Box<dyn Error>> {
+    let length = read_var_int(reader)?;
+    if !(1..=MAX_PACKET_LENGTH).contains(&length) {
+        return Err(format!("Invalid packet length: {}", length).into());
+    }
+    let mut packet = vec![0; length as usize];
+    reader.read_exact(&mut packet)?;
+    Ok(packet)
+}
+
+/// Starts a sleeping server after a player tried to join.
+fn wake_server(server_id: u64) {
+    let result = Server::get_server(server_id).and_then(|mut server| server.start_server());
+    if let Err(e) = result {
+        warn!("Failed to wake server {}: {}", server_id, e);
+        // The server keeps sleeping if it's listening for players again, otherwise it isn't sleeping anymore.
+        if is_running(server_id) || is_listening(server_id) {
+            return;
+        }
+        if let Err(e) = update_server_status(server_id, ServerStatus::Offline) {
+            warn!("Failed to update server status: {}", e);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use actix_web::dev::ServerHandle;
+use actix_web::web;
+use configuration::config::CONFIG;
+use log::{error, info, warn};
+use scheduler::stop_ticking_schedules;
+use std::process::exit;
+use std::time::Duration;
+
+/// Waits for SIGINT or SIGTERM, then stops the managed servers before stopping the web server.
+///
+/// The web server is started with its own signal handling disabled, so it keeps serving the
+/// status of the servers while they shut down. A second signal exits right away.
+///
+/// # Arguments
+/// * `server` - The handle of the web server, stopped once the servers are.
+pub async fn handle_shutdown_signals(server: ServerHandle) {
+    wait_for_signal().await;
+    info!("Shutting down, press Ctrl+C again to exit immediately");
+    actix_web::rt::spawn(async {
+        wait_for_signal().await;
+        warn!("Exiting without waiting for the servers to stop");
+        exit(1);
+    });
+
+    // Scheduled commands and restarts would start servers back up.
+    stop_ticking_schedules!();
+
+    if CONFIG.stop_servers_on_shutdown {
+        let timeout = Duration::from_secs(CONFIG.shutdown_timeout_seconds);
+        match web::block(move || servers::server_process::stop_all_servers(timeout)).await {
+            Ok(stopped) => info!("Stopped {} servers", stopped),
+            Err(e) => error!("Failed to stop the servers: {}", e),
+        }
+    } else {
+        info!("Leaving the servers running, they're adopted when the panel starts again");
+    }
+
+    server.stop(true).await;
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(terminate) => terminate,
+        Err(e) => {
+            error!("Failed to listen for SIGTERM: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        error!("Failed to listen for Ctrl+C: {}", e);
+    }
+}
@@ -8,6 +8,12 @@ import $ from "jquery";
 import {useSelectedServer} from "../../../providers/SelectedServerProvider.tsx";
 import {FileItem} from "../../../ts/file-system.ts";
 
+interface ConsoleLine
+{
+    source: "stdout" | "stderr";
+    line: string;
+}
+
 interface LogOutputProps
 {
     file: FileItem | null;
@@ -50,6 +56,24 @@ export default function LogOutput(props: LogOutputProps)
     useEffect(() =>
     {
 
+        if (server && !props.file)
+        {
+            // Without a log file, follow the live console, which starts with the server's scrollback.
+            setLog("");
+            const consoleServerSideEvent = new EventSource(`/api/server/${server.id}/console/sse`);
+            consoleServerSideEvent.onerror = (e) => console.error("Error connecting to console server side event", e);
+            consoleServerSideEvent.addEventListener("console_line", (event) =>
+            {
+                const line: ConsoleLine = JSON.parse(event.data);
+                setLog(log => `${log}${line.source === "stderr" ? "[stderr] " : ""}${line.line}\n`);
+                handleScrollLock();
+            });
+            return () =>
+            {
+                consoleServerSideEvent.close();
+            };
+        }
+
         if (server && props.file)
         {
             const consoleServerSideEvent = new EventSource(`/api/server/${server.id}/console/sse?log_file=${props.file?.name ?? ""}`);
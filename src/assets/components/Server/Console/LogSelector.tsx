@@ -7,7 +7,7 @@ import OSelect from "../../Extends/OSelect.tsx";
 interface LogSelectorProps
 {
     selectedLogFile: FileItem | null;
-    onSelectionChange: (file: FileItem) => void;
+    onSelectionChange: (file: FileItem | null) => void;
 }
 
 export default function LogSelector(props: LogSelectorProps)
@@ -22,17 +22,12 @@ export default function LogSelector(props: LogSelectorProps)
                 .files("/logs/")
                 .then(files => files.entries)
                 .then(files => files.filter(i => i.type === "Log File"))
-                .then(files =>
-                {
-                    setFiles(files);
-                    if (files.length > 0 && !props.selectedLogFile)
-                        props.onSelectionChange(files[0]);
-                });
+                .then(setFiles);
     }, []);
 
     return (
         <OSelect
-            selectedKeys={[props.selectedLogFile ? `log-file-${props.selectedLogFile.name}` : ""]}
+            selectedKeys={[props.selectedLogFile ? `log-file-${props.selectedLogFile.name}` : "live-console"]}
             className={"w-[200px]"}
             label={"Log File"}
             placeholder={"Select a log file..."}
@@ -40,15 +35,23 @@ export default function LogSelector(props: LogSelectorProps)
             disallowEmptySelection
             onSelectionChange={(key) =>
             {
+                if (key.currentKey === "live-console")
+                {
+                    props.onSelectionChange(null);
+                    return;
+                }
                 const file = files.find(i => i.name === key.currentKey?.replace("log-file-", ""));
                 if (file)
                     props.onSelectionChange(file);
 
             }}
         >
-            {files.map((file) => (
-                <SelectItem key={`log-file-${file.name}`}>{file.name}</SelectItem>
-            ))}
+            {[
+                <SelectItem key={"live-console"}>Live Console</SelectItem>,
+                ...files.map((file) => (
+                    <SelectItem key={`log-file-${file.name}`}>{file.name}</SelectItem>
+                ))
+            ]}
         </OSelect>
     );
 }
\ No newline at end of file
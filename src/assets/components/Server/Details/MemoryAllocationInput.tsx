@@ -9,7 +9,8 @@ export default function MemoryAllocationInput()
 {
     const [minMemory, setMinMemory] = useState<string>("");
     const [maxMemory, setMaxMemory] = useState<string>("");
-    const systemMemoryCapacity = 32;
+    const systemMemoryCapacity = 32 * 1024;
+    const minimumMemory = 512;
     const {server} = useSelectedServer();
     const {auth} = useAuth();
 
@@ -33,7 +34,7 @@ export default function MemoryAllocationInput()
             Number.isNaN(Number.parseInt(maxMemory.replace(/\D/g, "")))
         )
             return;
-        console.log(`Updating memory allocation for ${server.name} to ${minMemory}GB - ${maxMemory}GB`);
+        console.log(`Updating memory allocation for ${server.name} to ${minMemory}MB - ${maxMemory}MB`);
         $.ajax({
             url: `/api/server/${server?.id}/settings?max-ram=${maxMemory}&min-ram=${minMemory}`,
             method: "POST",
@@ -53,8 +54,8 @@ export default function MemoryAllocationInput()
                     className={"w-[100px]"}
                     value={minMemory}
                     onValueChange={value => setMinMemory(value.replace(/\D/g, ""))}
-                    onFocusChange={isFocused => !isFocused && setMinMemory((Number.isNaN(Number.parseInt(minMemory.replace(/\D/g, ""))) ? 1 : Number.parseInt(minMemory.replace(/\D/g, ""))).toString())}
-                    endContent={<p className={"text-medium text-neutral-400"}>GB</p>}
+                    onFocusChange={isFocused => !isFocused && setMinMemory((Number.isNaN(Number.parseInt(minMemory.replace(/\D/g, ""))) ? minimumMemory : Number.parseInt(minMemory.replace(/\D/g, ""))).toString())}
+                    endContent={<p className={"text-medium text-neutral-400"}>MB</p>}
                     aria-label="Minimum Memory"
                 />
                 <OInput
@@ -63,23 +64,23 @@ export default function MemoryAllocationInput()
                     value={maxMemory}
                     onValueChange={value => setMaxMemory(value.replace(/\D/g, ""))}
                     onFocusChange={isFocused => !isFocused && setMaxMemory((Number.isNaN(Number.parseInt(maxMemory.replace(/\D/g, ""))) ? systemMemoryCapacity : Number.parseInt(maxMemory.replace(/\D/g, ""))).toString())}
-                    endContent={<p className={"text-medium text-neutral-400"}>GB</p>}
+                    endContent={<p className={"text-medium text-neutral-400"}>MB</p>}
                     aria-label="Maximum Memory"
                 />
             </div>
             <Slider
-                step={1}
-                minValue={1}
+                step={minimumMemory}
+                minValue={minimumMemory}
                 maxValue={systemMemoryCapacity}
                 showTooltip
-                showSteps={systemMemoryCapacity <= 32}
+                showSteps={systemMemoryCapacity / minimumMemory <= 32}
                 value={
                     [
-                        Number.isNaN(Number.parseInt(minMemory.replace(/\D/g, ""))) ? 1 : Number.parseInt(minMemory.replace(/\D/g, "")),
+                        Number.isNaN(Number.parseInt(minMemory.replace(/\D/g, ""))) ? minimumMemory : Number.parseInt(minMemory.replace(/\D/g, "")),
                         Number.isNaN(Number.parseInt(maxMemory.replace(/\D/g, ""))) ? systemMemoryCapacity : Number.parseInt(maxMemory.replace(/\D/g, ""))
                     ]
                 }
-                formatOptions={{style: "unit", unit: "gigabyte"}}
+                formatOptions={{style: "unit", unit: "megabyte"}}
                 aria-label="Memory Allocation Slider"
                 onChange={(value) =>
                 {